@@ -0,0 +1,192 @@
+//! Serialization into `futures_io::AsyncWrite`rs and deserialization from
+//! `futures_io::AsyncRead`ers, which work with any async runtime.
+//!
+//! `Options::serialize_into_async` and `Options::deserialize_from_async` use these directly.
+//!
+//! Serde itself is synchronous, so a value is serialized into a buffer that is then written
+//! out. It is deserialized straight from the reader, which is only asked for the bytes the
+//! deserializer needs, so nothing past the end of the value is read and values can be read back
+//! to back from the same reader.
+//!
+//! The bytes of the value are kept as they are read. If the reader is pending in the middle of
+//! the value, deserialization can not be suspended, so it stops there and starts over on the
+//! kept bytes once the read it stopped at can be completed. A value is therefore deserialized
+//! once more for every time the reader is pending in the middle of it, and not at all again
+//! while the reader has its bytes ready. Large values that arrive slowly are better sent as
+//! frames, for example with a length prefix that is read first.
+
+use core::future::poll_fn;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use ::futures_io::{AsyncRead, AsyncWrite};
+use alloc::vec::Vec;
+
+use crate::config::Options;
+use crate::de::read::BincodeRead;
+use crate::io;
+use crate::{ErrorKind, Result};
+
+/// The size of the first read into an empty buffer, which then doubles with every read.
+const READ_CHUNK: usize = 4096;
+
+/// Reads from `reader` until `buffer` holds `len` bytes. The buffer only grows as bytes arrive,
+/// so a length read from untrusted input is never allocated up front.
+fn poll_fill<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    cx: &mut Context,
+    buffer: &mut Vec<u8>,
+    len: usize,
+) -> Poll<io::Result<()>> {
+    while buffer.len() < len {
+        let start = buffer.len();
+        buffer.resize(start + (len - start).min(start.max(READ_CHUNK)), 0);
+        match Pin::new(&mut *reader).poll_read(cx, &mut buffer[start..]) {
+            Poll::Ready(Ok(n)) if n > 0 => buffer.truncate(start + n),
+            Poll::Ready(Ok(_)) => {
+                buffer.truncate(start);
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => {
+                buffer.truncate(start)
+            }
+            Poll::Ready(Err(e)) => {
+                buffer.truncate(start);
+                return Poll::Ready(Err(e));
+            }
+            Poll::Pending => {
+                buffer.truncate(start);
+                return Poll::Pending;
+            }
+        }
+    }
+    Poll::Ready(Ok(()))
+}
+
+/// A BincodeRead implementation that reads the bytes of a value from an `AsyncRead`er as they
+/// are asked for, and keeps them so that deserialization can start over on them.
+struct PollReader<'a, 'b, R> {
+    reader: &'a mut R,
+    cx: &'a mut Context<'b>,
+    /// The bytes of the value that have been read from the reader.
+    buffer: &'a mut Vec<u8>,
+    /// The number of bytes of `buffer` that have been deserialized.
+    pos: usize,
+    /// The length `buffer` needed when the reader was pending.
+    pending: Option<usize>,
+}
+
+impl<'a, 'b, R: AsyncRead + Unpin> PollReader<'a, 'b, R> {
+    fn get_byte_slice(&mut self, length: usize) -> io::Result<&[u8]> {
+        let end = self.pos.saturating_add(length);
+        match poll_fill(self.reader, self.cx, self.buffer, end) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(e)) => return Err(e),
+            Poll::Pending => {
+                self.pending = Some(end);
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+        }
+        let start = self.pos;
+        self.pos = end;
+        Ok(&self.buffer[start..end])
+    }
+}
+
+impl<'a, 'b, R: AsyncRead + Unpin> io::Read for PollReader<'a, 'b, R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        out.copy_from_slice(self.get_byte_slice(out.len())?);
+        Ok(out.len())
+    }
+
+    fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
+        self.read(out).map(|_| ())
+    }
+}
+
+impl<'de, 'a, 'b, R: AsyncRead + Unpin> BincodeRead<'de> for PollReader<'a, 'b, R> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match core::str::from_utf8(self.get_byte_slice(length)?) {
+            Ok(s) => visitor.visit_str(s),
+            Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e).into()),
+        }
+    }
+
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        Ok(self.get_byte_slice(length)?.to_vec())
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_bytes(self.get_byte_slice(length)?)
+    }
+
+    fn forward_reads_allocate(&self) -> bool {
+        true
+    }
+}
+
+async fn write_all<W: AsyncWrite + Unpin>(writer: &mut W, mut buf: &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, buf)).await {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => buf = &buf[n..],
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn serialize_into<W, T, O>(mut writer: W, value: &T, options: O) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: ?Sized + serde::Serialize,
+    O: Options,
+{
+    let bytes = crate::internal::serialize(value, options)?;
+    write_all(&mut writer, &bytes).await.map_err(Into::into)
+}
+
+pub(crate) async fn deserialize_from<R, T, O>(mut reader: R, options: O) -> Result<T>
+where
+    R: AsyncRead + Unpin,
+    T: serde::de::DeserializeOwned,
+    O: Options + Clone,
+{
+    let mut buffer = Vec::new();
+    // the length of `buffer` the last attempt needed when the reader was pending
+    let mut wanted = 0;
+    poll_fn(|cx| {
+        // the read the last attempt stopped at is completed before it starts over
+        match poll_fill(&mut reader, cx, &mut buffer, wanted) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        // the size limit is charged before every read, so a value that is too large fails
+        // before the bytes it is missing are read
+        let poll_reader = PollReader {
+            reader: &mut reader,
+            cx,
+            buffer: &mut buffer,
+            pos: 0,
+            pending: None,
+        };
+        let mut deserializer =
+            crate::de::Deserializer::with_bincode_read(poll_reader, options.clone());
+        let result = serde::Deserialize::deserialize(&mut deserializer);
+        if let Some(end) = deserializer.reader.pending {
+            wanted = end;
+            return Poll::Pending;
+        }
+        Poll::Ready(result.map_err(|err| deserializer.with_error_context(err)))
+    })
+    .await
+}
@@ -0,0 +1,222 @@
+//! Integration with the `bytes` crate.
+//!
+//! `Options::serialize_into_buf` writes into any `bytes::BufMut`, and
+//! `Options::deserialize_from_buf` reads from any `bytes::Buf` through a [`BufReader`].
+//!
+//! `Options::deserialize_from_bytes` borrows from a `bytes::Bytes` instead. With the `std`
+//! feature, `Bytes` fields that are (de)serialized [`shared`] are then handed out as slices of
+//! it without copying.
+//!
+//! Serde gives a field's `Deserialize` impl no way to reach the `Bytes` being deserialized, so
+//! [`shared`] finds it through a thread-local that `deserialize_from_bytes` sets for the
+//! duration of the call, which needs `std`. Other byte fields are not covered: a
+//! `#[serde(with = "serde_bytes")]` field borrows from the input if it is a `&[u8]` and is
+//! copied otherwise, the same as with `deserialize`.
+
+#[cfg(feature = "std")]
+use core::cell::RefCell;
+
+use ::alloc::vec::Vec;
+
+use ::bytes::{Buf, BufMut, Bytes};
+
+use crate::de::read::BincodeRead;
+use crate::io;
+use crate::{ErrorKind, Result};
+
+/// A BincodeRead implementation for `bytes::Buf`s
+///
+/// Strings and byte arrays are passed on straight from the buffer when they fit in its current
+/// chunk, and are only copied when they span chunks.
+pub struct BufReader<B> {
+    buf: B,
+    temp_buffer: Vec<u8>,
+}
+
+impl<B: Buf> BufReader<B> {
+    /// Constructs a BufReader
+    pub fn new(buf: B) -> BufReader<B> {
+        BufReader {
+            buf,
+            temp_buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying buffer, advanced past everything that was read.
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+
+    /// Passes the next `length` bytes to `f`, copying them only if they span chunks.
+    fn with_bytes<T, F>(&mut self, length: usize, f: F) -> Result<T>
+    where
+        F: FnOnce(&[u8]) -> Result<T>,
+    {
+        if length > self.buf.remaining() {
+            return Err(unexpected_eof());
+        }
+        if self.buf.chunk().len() >= length {
+            let result = f(&self.buf.chunk()[..length]);
+            self.buf.advance(length);
+            result
+        } else {
+            self.temp_buffer.resize(length, 0);
+            self.buf.copy_to_slice(&mut self.temp_buffer);
+            f(&self.temp_buffer)
+        }
+    }
+}
+
+fn unexpected_eof() -> crate::Error {
+    ErrorKind::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "")).into()
+}
+
+impl<B: Buf> io::Read for BufReader<B> {
+    #[inline(always)]
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let n = core::cmp::min(out.len(), self.buf.remaining());
+        self.buf.copy_to_slice(&mut out[..n]);
+        Ok(n)
+    }
+
+    #[inline(always)]
+    fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
+        if out.len() > self.buf.remaining() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.buf.copy_to_slice(out);
+        Ok(())
+    }
+}
+
+/// A `Write`r that puts everything written to it into a `bytes::BufMut`.
+pub(crate) struct BufWriter<'a, B>(pub(crate) &'a mut B);
+
+impl<'a, B: BufMut> io::Write for BufWriter<'a, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = core::cmp::min(buf.len(), self.0.remaining_mut());
+        self.0.put_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, B: Buf> BincodeRead<'a> for BufReader<B> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'a>,
+    {
+        self.with_bytes(length, |bytes| match core::str::from_utf8(bytes) {
+            Ok(s) => visitor.visit_str(s),
+            Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e).into()),
+        })
+    }
+
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        if length > self.buf.remaining() {
+            return Err(unexpected_eof());
+        }
+        Ok(self.buf.copy_to_bytes(length).into())
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'a>,
+    {
+        self.with_bytes(length, |bytes| visitor.visit_bytes(bytes))
+    }
+
+    fn forward_reads_allocate(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// The `Bytes` that `Options::deserialize_from_bytes` is deserializing from on this thread.
+    static SOURCE: RefCell<Option<Bytes>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous source when dropped, even if the deserialization that set it panics.
+#[cfg(feature = "std")]
+struct RestoreSource(Option<Bytes>);
+
+#[cfg(feature = "std")]
+impl Drop for RestoreSource {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SOURCE.with(|source| source.replace(previous));
+    }
+}
+
+/// Runs `f` with `bytes` as the source that [`shared`] fields are sliced from.
+#[cfg(feature = "std")]
+pub(crate) fn with_source<T, F: FnOnce() -> T>(bytes: &Bytes, f: F) -> T {
+    let _restore = RestoreSource(SOURCE.with(|source| source.replace(Some(bytes.clone()))));
+    f()
+}
+
+/// Without `std` there is no thread-local to hold the source, so [`shared`] fields are copied.
+#[cfg(not(feature = "std"))]
+pub(crate) fn with_source<T, F: FnOnce() -> T>(_bytes: &Bytes, f: F) -> T {
+    f()
+}
+
+/// (De)serializes a `bytes::Bytes` field as a byte array, for use with
+/// `#[serde(with = "bincode::buf::shared")]`.
+///
+/// When the field is deserialized by `Options::deserialize_from_bytes` with the `std` feature,
+/// it is a slice of the input that shares its memory. Otherwise its bytes are copied.
+pub mod shared {
+    use core::fmt;
+
+    use ::bytes::Bytes;
+    use serde::{de, Deserializer, Serializer};
+
+    /// Serializes `bytes` as a byte array.
+    pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
+
+    /// Deserializes a byte array, sharing the memory of the input if it is borrowed from the
+    /// `Bytes` being deserialized.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> de::Visitor<'de> for BytesVisitor {
+        type Value = Bytes;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a byte array")
+        }
+
+        #[cfg(feature = "std")]
+        fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Bytes, E> {
+            Ok(super::SOURCE.with(|source| match *source.borrow() {
+                Some(ref source) if contains(source, v) => source.slice_ref(v),
+                _ => Bytes::copy_from_slice(v),
+            }))
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Bytes, E> {
+            Ok(Bytes::copy_from_slice(v))
+        }
+
+        fn visit_byte_buf<E>(self, v: ::alloc::vec::Vec<u8>) -> Result<Bytes, E> {
+            Ok(Bytes::from(v))
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn contains(source: &[u8], slice: &[u8]) -> bool {
+        let start = source.as_ptr() as usize;
+        let slice_start = slice.as_ptr() as usize;
+        slice_start >= start && slice_start + slice.len() <= start + source.len()
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright (c) 2015 Andrew Gallant
 
-use core2::io;
-use core2::io::Result;
+use crate::io;
+use crate::io::Result;
 use core::ptr::copy_nonoverlapping;
 
 #[derive(Copy, Clone)]
@@ -208,6 +208,8 @@ pub trait ByteOrder: Clone + Copy {
     }
 }
 
+// kept complete, although the int encodings do not use the signed methods
+#[allow(dead_code)]
 pub trait ReadBytesExt: io::Read {
     #[inline]
     fn read_u8(&mut self) -> Result<u8> {
@@ -298,6 +300,7 @@ pub trait ReadBytesExt: io::Read {
 
 impl<R: io::Read + ?Sized> ReadBytesExt for R {}
 
+#[allow(dead_code)]
 pub trait WriteBytesExt: io::Write {
     #[inline]
     fn write_u8(&mut self, n: u8) -> Result<()> {
@@ -0,0 +1,150 @@
+//! A `tokio_util::codec` implementation that sends every value as a frame prefixed with its
+//! length.
+//!
+//! ```no_run
+//! # use bincode::{codec::BincodeCodec, DefaultOptions, Options};
+//! # use futures::{SinkExt, StreamExt};
+//! # use serde_derive::{Deserialize, Serialize};
+//! use tokio::io::{AsyncRead, AsyncWrite};
+//! use tokio_util::codec::Framed;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Message {
+//!     id: u32,
+//!     body: String,
+//! }
+//!
+//! async fn echo<S: AsyncRead + AsyncWrite + Unpin>(stream: S) -> bincode::Result<()> {
+//!     let options = DefaultOptions::new().with_limit(64 * 1024);
+//!     let mut framed = Framed::new(stream, BincodeCodec::<Message, _>::new(options));
+//!     while let Some(message) = framed.next().await {
+//!         framed.send(message?).await?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use core::marker::PhantomData;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::config::{BincodeByteOrder, Options, SizeLimit};
+pub use crate::frame::FrameHeader;
+use crate::{Error, ErrorKind, Result};
+
+/// Encodes and decodes values of type `T` as length-prefixed frames using the options `O`.
+///
+/// A size limit set with `Options::with_limit` bounds the length of every frame, and so does an
+/// allocation limit set with `Options::with_alloc_limit`. A frame whose header announces more
+/// bytes than either is rejected as soon as the header has arrived, before any of it is
+/// buffered, and a value that would need a longer frame is not encoded. Without a limit the
+/// announced length is not trusted, so the buffer only grows as the frame arrives.
+pub struct BincodeCodec<T, O: Options> {
+    options: O,
+    header: FrameHeader,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, O: Options + Clone> BincodeCodec<T, O> {
+    /// Creates a codec with a `FrameHeader::U32` length in front of every frame.
+    pub fn new(options: O) -> BincodeCodec<T, O> {
+        BincodeCodec {
+            options,
+            header: FrameHeader::U32,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the width of the length in front of every frame.
+    pub fn with_header(mut self, header: FrameHeader) -> BincodeCodec<T, O> {
+        self.header = header;
+        self
+    }
+}
+
+impl<T, O: Options + Clone> Clone for BincodeCodec<T, O> {
+    fn clone(&self) -> BincodeCodec<T, O> {
+        BincodeCodec {
+            options: self.options.clone(),
+            header: self.header,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, O> Encoder<T> for BincodeCodec<T, O>
+where
+    T: serde::Serialize,
+    O: Options + Clone,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<()> {
+        let mut options = self.options.clone();
+        // fails if the value is larger than the size limit
+        let len = crate::internal::serialized_size(&item, &mut options)?;
+        self.header.check_len(len)?;
+
+        let width = self.header.width(len);
+        dst.reserve(width + len as usize);
+        let start = dst.len();
+        dst.put_bytes(0, width);
+        self.header
+            .write::<<O::Endian as BincodeByteOrder>::Endian>(&mut dst[start..], len);
+        crate::internal::serialize_into(dst.writer(), &item, options.with_no_limit())
+    }
+}
+
+impl<T, O> Decoder for BincodeCodec<T, O>
+where
+    T: serde::de::DeserializeOwned,
+    O: Options + Clone,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        let width = match src.first() {
+            Some(&first) => self.header.width_from_first(first)?,
+            None => return Ok(None),
+        };
+        if src.len() < width {
+            return Ok(None);
+        }
+
+        let mut options = self.options.clone();
+        let len = self
+            .header
+            .read::<<O::Endian as BincodeByteOrder>::Endian>(&src[..width]);
+        let limit = options.limit().limit();
+        if let Some(limit) = limit {
+            if len > limit {
+                return Err(ErrorKind::SizeLimit.into());
+            }
+        }
+        let alloc_limit = options.alloc_limit().limit();
+        if let Some(remaining) = alloc_limit {
+            if len > remaining {
+                return Err(ErrorKind::AllocationLimit {
+                    requested: len,
+                    remaining,
+                }
+                .into());
+            }
+        }
+        // a frame that does not fit in memory is treated like one that exceeds the limit
+        let len = usize::try_from(len).map_err(|_| ErrorKind::SizeLimit)?;
+
+        if src.len() - width < len {
+            // only a length that a limit has vetted is worth reserving for up front
+            if limit.is_some() || alloc_limit.is_some() {
+                src.reserve(width + len - src.len());
+            }
+            return Ok(None);
+        }
+        src.advance(width);
+        let frame = src.split_to(len);
+        crate::internal::deserialize(&frame[..], options).map(Some)
+    }
+}
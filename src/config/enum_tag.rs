@@ -0,0 +1,153 @@
+use crate::io::Write;
+
+use super::len::Varint;
+use super::{IntEncoding, Options, VarintEncoding};
+use crate::de::read::BincodeRead;
+use crate::error::{ErrorKind, Result};
+
+/// A trait for encoding the index of an enum variant.
+///
+/// It is implemented by `u8`, `u16` and `u32` for fixed-width tags, by `Varint` for varint tags
+/// and by `IntEncodingTag`, the default, which encodes the index as a u32 with the int encoding.
+pub trait EnumTag {
+    /// Gets the size (in bytes) that a variant index would be serialized to, or an error if the
+    /// index does not fit in the tag.
+    fn tag_size<O: Options>(index: u32) -> Result<u64>;
+
+    /// Serializes a variant index.
+    fn serialize_tag<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        index: u32,
+    ) -> Result<()>;
+
+    /// Deserializes a variant index.
+    fn deserialize_tag<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<u32>;
+}
+
+/// Variant indices are encoded as a u32 with the configured int encoding. This is the default.
+#[derive(Copy, Clone)]
+pub struct IntEncodingTag;
+
+fn check_index(index: u32, max: u32) -> Result<()> {
+    if index <= max {
+        Ok(())
+    } else {
+        Err(ErrorKind::EnumTagOverflow { index, max }.into())
+    }
+}
+
+impl EnumTag for IntEncodingTag {
+    #[inline(always)]
+    fn tag_size<O: Options>(index: u32) -> Result<u64> {
+        Ok(O::IntEncoding::u32_size(index))
+    }
+
+    #[inline(always)]
+    fn serialize_tag<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        index: u32,
+    ) -> Result<()> {
+        O::IntEncoding::serialize_u32(ser, index)
+    }
+
+    #[inline(always)]
+    fn deserialize_tag<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<u32> {
+        O::IntEncoding::deserialize_u32(de)
+    }
+}
+
+impl EnumTag for u8 {
+    #[inline(always)]
+    fn tag_size<O: Options>(index: u32) -> Result<u64> {
+        check_index(index, u8::MAX as u32)?;
+        Ok(1)
+    }
+
+    #[inline(always)]
+    fn serialize_tag<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        index: u32,
+    ) -> Result<()> {
+        check_index(index, u8::MAX as u32)?;
+        ser.serialize_byte(index as u8)
+    }
+
+    #[inline(always)]
+    fn deserialize_tag<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<u32> {
+        Ok(de.deserialize_byte()? as u32)
+    }
+}
+
+impl EnumTag for u16 {
+    #[inline(always)]
+    fn tag_size<O: Options>(index: u32) -> Result<u64> {
+        check_index(index, u16::MAX as u32)?;
+        Ok(2)
+    }
+
+    #[inline(always)]
+    fn serialize_tag<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        index: u32,
+    ) -> Result<()> {
+        check_index(index, u16::MAX as u32)?;
+        ser.serialize_literal_u16(index as u16)
+    }
+
+    #[inline(always)]
+    fn deserialize_tag<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<u32> {
+        Ok(de.deserialize_literal_u16()? as u32)
+    }
+}
+
+impl EnumTag for u32 {
+    #[inline(always)]
+    fn tag_size<O: Options>(_: u32) -> Result<u64> {
+        Ok(4)
+    }
+
+    #[inline(always)]
+    fn serialize_tag<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        index: u32,
+    ) -> Result<()> {
+        ser.serialize_literal_u32(index)
+    }
+
+    #[inline(always)]
+    fn deserialize_tag<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<u32> {
+        de.deserialize_literal_u32()
+    }
+}
+
+impl EnumTag for Varint {
+    #[inline(always)]
+    fn tag_size<O: Options>(index: u32) -> Result<u64> {
+        Ok(VarintEncoding::u32_size(index))
+    }
+
+    #[inline(always)]
+    fn serialize_tag<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        index: u32,
+    ) -> Result<()> {
+        VarintEncoding::serialize_u32(ser, index)
+    }
+
+    #[inline(always)]
+    fn deserialize_tag<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<u32> {
+        VarintEncoding::deserialize_u32(de)
+    }
+}
@@ -0,0 +1,29 @@
+/// A trait for deciding whether deserialization errors carry the location they occurred at.
+pub trait ErrorContext {
+    /// Returns true if the deserializer should track the path to the value being deserialized,
+    /// so that errors can be wrapped in an `ErrorKind::Context`.
+    fn enabled() -> bool;
+}
+
+/// An ErrorContext config that returns deserialization errors as they are produced.
+#[derive(Copy, Clone)]
+pub struct NoErrorContext;
+
+/// An ErrorContext config that wraps deserialization errors in an `ErrorKind::Context` holding
+/// the byte offset and the path of the value that failed, such as `Block.transactions[12]`.
+#[derive(Copy, Clone)]
+pub struct TrackErrorContext;
+
+impl ErrorContext for NoErrorContext {
+    #[inline(always)]
+    fn enabled() -> bool {
+        false
+    }
+}
+
+impl ErrorContext for TrackErrorContext {
+    #[inline(always)]
+    fn enabled() -> bool {
+        true
+    }
+}
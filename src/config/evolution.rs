@@ -0,0 +1,72 @@
+/// A trait for deciding whether the fields of a struct are prefixed by their length in bytes.
+pub trait StructEvolution {
+    /// Returns true if the serializer should write the length in bytes of the fields of each
+    /// struct before them, which lets the deserializer skip fields it does not know and leave
+    /// out fields that are missing.
+    fn enabled() -> bool;
+}
+
+/// A StructEvolution config that writes the fields of a struct one after another, like a tuple,
+/// as bincode always has.
+#[derive(Copy, Clone)]
+pub struct FixedStructs;
+
+/// A StructEvolution config that writes the length in bytes of the fields of each struct and
+/// struct variant before them, with the length encoding.
+///
+/// Fields can then be appended to a struct without breaking data that is already stored: a
+/// reader with fewer fields skips the ones it does not know, and a reader with more fields
+/// fills in the missing ones, which must be marked `#[serde(default)]`.
+#[derive(Copy, Clone)]
+pub struct LengthPrefixedStructs;
+
+impl StructEvolution for FixedStructs {
+    #[inline(always)]
+    fn enabled() -> bool {
+        false
+    }
+}
+
+impl StructEvolution for LengthPrefixedStructs {
+    #[inline(always)]
+    fn enabled() -> bool {
+        true
+    }
+}
+
+/// A trait for deciding whether the payload of an enum variant is prefixed by its length in
+/// bytes.
+pub trait VariantEvolution {
+    /// Returns true if the serializer should write the length in bytes of the payload of each
+    /// enum variant after its index, which lets the deserializer skip the payload of variants it
+    /// does not know.
+    fn enabled() -> bool;
+}
+
+/// A VariantEvolution config that writes the payload of an enum variant right after its index,
+/// as bincode always has.
+#[derive(Copy, Clone)]
+pub struct FixedVariants;
+
+/// A VariantEvolution config that writes the length in bytes of the payload of each enum
+/// variant between its index and the payload, with the length encoding.
+///
+/// Variants can then be appended to an enum without breaking readers that do not know them yet:
+/// an unknown variant is read as the enum's `#[serde(other)]` variant, and its payload is
+/// skipped, or kept along with its payload by reading the enum as a `de::OrUnknown`.
+#[derive(Copy, Clone)]
+pub struct LengthPrefixedVariants;
+
+impl VariantEvolution for FixedVariants {
+    #[inline(always)]
+    fn enabled() -> bool {
+        false
+    }
+}
+
+impl VariantEvolution for LengthPrefixedVariants {
+    #[inline(always)]
+    fn enabled() -> bool {
+        true
+    }
+}
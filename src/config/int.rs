@@ -1,5 +1,4 @@
-use alloc::{boxed::Box, format, string::ToString};
-use core2::io::Write;
+use crate::io::Write;
 use core::mem::size_of;
 
 use super::Options;
@@ -26,6 +25,13 @@ pub trait IntEncoding {
         Self::u64_size(len as u64)
     }
 
+    /// Whether every sequence length is serialized to the same number of bytes, which allows
+    /// a length to be overwritten in place once it is known.
+    #[inline(always)]
+    fn len_is_fixed_width() -> bool {
+        false
+    }
+
     /// Serializes a sequence length.
     #[inline(always)]
     fn serialize_len<W: Write, O: Options>(
@@ -171,9 +177,9 @@ pub struct FixintEncoding;
 pub struct VarintEncoding;
 
 const SINGLE_BYTE_MAX: u8 = 250;
-const U16_BYTE: u8 = 251;
-const U32_BYTE: u8 = 252;
-const U64_BYTE: u8 = 253;
+pub(crate) const U16_BYTE: u8 = 251;
+pub(crate) const U32_BYTE: u8 = 252;
+pub(crate) const U64_BYTE: u8 = 253;
 const U128_BYTE: u8 = 254;
 const DESERIALIZE_EXTENSION_POINT_ERR: &str = r#"
 Byte 255 is treated as an extension point; it should not be encoding anything.
@@ -248,13 +254,12 @@ impl VarintEncoding {
             U16_BYTE => Ok(de.deserialize_literal_u16()? as u64),
             U32_BYTE => Ok(de.deserialize_literal_u32()? as u64),
             U64_BYTE => de.deserialize_literal_u64(),
-            U128_BYTE => Err(Box::new(ErrorKind::Custom(
+            U128_BYTE => Err(ErrorKind::Custom(
                 "Invalid value (u128 range): you may have a version or configuration disagreement?"
-                    .to_string(),
-            ))),
-            _ => Err(Box::new(ErrorKind::Custom(
-                DESERIALIZE_EXTENSION_POINT_ERR.to_string(),
-            ))),
+                    .into(),
+            )
+            .into()),
+            _ => Err(ErrorKind::Custom(DESERIALIZE_EXTENSION_POINT_ERR.into()).into()),
         }
     }
 
@@ -322,13 +327,18 @@ impl VarintEncoding {
                 U32_BYTE => Ok(de.deserialize_literal_u32()? as u128),
                 U64_BYTE => Ok(de.deserialize_literal_u64()? as u128),
                 U128_BYTE => de.deserialize_literal_u128(),
-                _ => Err(Box::new(ErrorKind::Custom(DESERIALIZE_EXTENSION_POINT_ERR.to_string()))),
+                _ => Err(ErrorKind::Custom(DESERIALIZE_EXTENSION_POINT_ERR.into()).into()),
             }
         }
     }
 }
 
 impl IntEncoding for FixintEncoding {
+    #[inline(always)]
+    fn len_is_fixed_width() -> bool {
+        true
+    }
+
     #[inline(always)]
     fn u16_size(_: u16) -> u64 {
         size_of::<u16>() as u64
@@ -591,35 +601,30 @@ impl IntEncoding for VarintEncoding {
     }
 }
 
-fn cast_u64_to_usize(n: u64) -> Result<usize> {
+pub(super) fn cast_u64_to_usize(n: u64) -> Result<usize> {
     if n <= usize::max_value() as u64 {
         Ok(n as usize)
     } else {
-        Err(Box::new(ErrorKind::Custom(format!(
+        Err(custom_error!(
             "Invalid size {}: sizes must fit in a usize (0 to {})",
             n,
             usize::max_value()
-        ))))
+        )
+        .into())
     }
 }
 fn cast_u64_to_u32(n: u64) -> Result<u32> {
     if n <= u32::max_value() as u64 {
         Ok(n as u32)
     } else {
-        Err(Box::new(ErrorKind::Custom(format!(
-            "Invalid u32 {}: you may have a version disagreement?",
-            n,
-        ))))
+        Err(custom_error!("Invalid u32 {}: you may have a version disagreement?", n).into())
     }
 }
 fn cast_u64_to_u16(n: u64) -> Result<u16> {
     if n <= u16::max_value() as u64 {
         Ok(n as u16)
     } else {
-        Err(Box::new(ErrorKind::Custom(format!(
-            "Invalid u16 {}: you may have a version disagreement?",
-            n,
-        ))))
+        Err(custom_error!("Invalid u16 {}: you may have a version disagreement?", n).into())
     }
 }
 
@@ -627,10 +632,7 @@ fn cast_i64_to_i32(n: i64) -> Result<i32> {
     if n <= i32::max_value() as i64 && n >= i32::min_value() as i64 {
         Ok(n as i32)
     } else {
-        Err(Box::new(ErrorKind::Custom(format!(
-            "Invalid i32 {}: you may have a version disagreement?",
-            n,
-        ))))
+        Err(custom_error!("Invalid i32 {}: you may have a version disagreement?", n).into())
     }
 }
 
@@ -638,10 +640,7 @@ fn cast_i64_to_i16(n: i64) -> Result<i16> {
     if n <= i16::max_value() as i64 && n >= i16::min_value() as i64 {
         Ok(n as i16)
     } else {
-        Err(Box::new(ErrorKind::Custom(format!(
-            "Invalid i16 {}: you may have a version disagreement?",
-            n,
-        ))))
+        Err(custom_error!("Invalid i16 {}: you may have a version disagreement?", n).into())
     }
 }
 
@@ -1,5 +1,8 @@
+#[cfg(feature = "alloc")]
+use crate::io::Read;
+use crate::io::Write;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
-use core2::io::{Read, Write};
 
 use self::EndianOption::*;
 use self::LimitOption::*;
@@ -147,6 +150,7 @@ impl Config {
     }
 
     /// Serializes a serializable object into a `Vec` of bytes using this configuration
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     pub fn serialize<T: ?Sized + serde::Serialize>(&self, t: &T) -> Result<Vec<u8>> {
         config_map!(self, opts => crate::internal::serialize(t, opts))
@@ -201,6 +205,7 @@ impl Config {
     /// Deserializes an object directly from a `Read`er using this configuration
     ///
     /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     pub fn deserialize_from<R: Read, T: serde::de::DeserializeOwned>(
         &self,
@@ -212,6 +217,7 @@ impl Config {
     /// Deserializes an object directly from a `Read`er with state `seed` using this configuration
     ///
     /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     pub fn deserialize_from_seed<'a, R: Read, T: serde::de::DeserializeSeed<'a>>(
         &self,
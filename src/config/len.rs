@@ -0,0 +1,295 @@
+use crate::io::Write;
+
+use super::int::cast_u64_to_usize;
+use super::{IntEncoding, Options};
+use crate::de::read::BincodeRead;
+use crate::error::{ErrorKind, Result};
+
+/// A trait for encoding the length prefix of sequences, maps, strings and byte arrays
+/// independently of how integers are encoded.
+pub trait LenEncoding {
+    /// Gets the size (in bytes) that a length would be serialized to, or an error if the length
+    /// does not fit in the encoding.
+    fn len_size<O: Options>(len: usize) -> Result<u64>;
+
+    /// Whether every length is serialized to the same number of bytes, which allows a length to
+    /// be overwritten in place once it is known.
+    fn len_is_fixed_width<O: Options>() -> bool;
+
+    /// Serializes a length.
+    fn serialize_len<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        len: usize,
+    ) -> Result<()>;
+
+    /// Deserializes a length.
+    fn deserialize_len<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<usize>;
+}
+
+/// Lengths are encoded as a u64 with the configured int encoding. This is the default.
+#[derive(Copy, Clone)]
+pub struct IntEncodingLen;
+
+/// Lengths are encoded as a single byte, so they must not exceed 255.
+#[derive(Copy, Clone)]
+pub struct FixU8;
+
+/// Lengths are encoded as a u16 with the configured endianness.
+#[derive(Copy, Clone)]
+pub struct FixU16;
+
+/// Lengths are encoded as a u32 with the configured endianness.
+#[derive(Copy, Clone)]
+pub struct FixU32;
+
+/// Lengths are encoded as a u64 with the configured endianness.
+#[derive(Copy, Clone)]
+pub struct FixU64;
+
+/// Lengths, or enum tags, are encoded as a varint, as described by `VarintEncoding`.
+#[derive(Copy, Clone)]
+pub struct Varint;
+
+/// Lengths are encoded in the compact-u16 format used by Solana's `short_vec`.
+///
+/// A length is split into groups of 7 bits, least significant first, and each group is written
+/// as a byte whose high bit is set if another group follows. Lengths therefore take 1 to 3
+/// bytes and must fit in a u16. Encodings that end in a superfluous zero byte are rejected, so
+/// that every length has exactly one encoding.
+#[derive(Copy, Clone)]
+pub struct CompactU16;
+
+impl LenEncoding for IntEncodingLen {
+    #[inline(always)]
+    fn len_size<O: Options>(len: usize) -> Result<u64> {
+        Ok(O::IntEncoding::len_size(len))
+    }
+
+    #[inline(always)]
+    fn len_is_fixed_width<O: Options>() -> bool {
+        O::IntEncoding::len_is_fixed_width()
+    }
+
+    #[inline(always)]
+    fn serialize_len<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        len: usize,
+    ) -> Result<()> {
+        O::IntEncoding::serialize_len(ser, len)
+    }
+
+    #[inline(always)]
+    fn deserialize_len<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<usize> {
+        O::IntEncoding::deserialize_len(de)
+    }
+}
+
+impl LenEncoding for FixU8 {
+    #[inline(always)]
+    fn len_size<O: Options>(len: usize) -> Result<u64> {
+        cast_len(len, u8::MAX as u64)?;
+        Ok(1)
+    }
+
+    #[inline(always)]
+    fn len_is_fixed_width<O: Options>() -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn serialize_len<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        len: usize,
+    ) -> Result<()> {
+        ser.serialize_byte(cast_len(len, u8::MAX as u64)? as u8)
+    }
+
+    #[inline(always)]
+    fn deserialize_len<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<usize> {
+        Ok(de.deserialize_byte()? as usize)
+    }
+}
+
+impl LenEncoding for FixU16 {
+    #[inline(always)]
+    fn len_size<O: Options>(len: usize) -> Result<u64> {
+        cast_len(len, u16::MAX as u64)?;
+        Ok(2)
+    }
+
+    #[inline(always)]
+    fn len_is_fixed_width<O: Options>() -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn serialize_len<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        len: usize,
+    ) -> Result<()> {
+        ser.serialize_literal_u16(cast_len(len, u16::MAX as u64)? as u16)
+    }
+
+    #[inline(always)]
+    fn deserialize_len<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<usize> {
+        Ok(de.deserialize_literal_u16()? as usize)
+    }
+}
+
+impl LenEncoding for FixU32 {
+    #[inline(always)]
+    fn len_size<O: Options>(len: usize) -> Result<u64> {
+        cast_len(len, u32::MAX as u64)?;
+        Ok(4)
+    }
+
+    #[inline(always)]
+    fn len_is_fixed_width<O: Options>() -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn serialize_len<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        len: usize,
+    ) -> Result<()> {
+        ser.serialize_literal_u32(cast_len(len, u32::MAX as u64)? as u32)
+    }
+
+    #[inline(always)]
+    fn deserialize_len<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<usize> {
+        cast_u64_to_usize(de.deserialize_literal_u32()? as u64)
+    }
+}
+
+impl LenEncoding for FixU64 {
+    #[inline(always)]
+    fn len_size<O: Options>(_: usize) -> Result<u64> {
+        Ok(8)
+    }
+
+    #[inline(always)]
+    fn len_is_fixed_width<O: Options>() -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn serialize_len<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        len: usize,
+    ) -> Result<()> {
+        ser.serialize_literal_u64(len as u64)
+    }
+
+    #[inline(always)]
+    fn deserialize_len<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<usize> {
+        cast_u64_to_usize(de.deserialize_literal_u64()?)
+    }
+}
+
+impl LenEncoding for Varint {
+    #[inline(always)]
+    fn len_size<O: Options>(len: usize) -> Result<u64> {
+        Ok(super::VarintEncoding::len_size(len))
+    }
+
+    #[inline(always)]
+    fn len_is_fixed_width<O: Options>() -> bool {
+        false
+    }
+
+    #[inline(always)]
+    fn serialize_len<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        len: usize,
+    ) -> Result<()> {
+        super::VarintEncoding::serialize_len(ser, len)
+    }
+
+    #[inline(always)]
+    fn deserialize_len<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<usize> {
+        super::VarintEncoding::deserialize_len(de)
+    }
+}
+
+impl LenEncoding for CompactU16 {
+    #[inline(always)]
+    fn len_size<O: Options>(len: usize) -> Result<u64> {
+        let len = cast_len(len, u16::MAX as u64)?;
+        Ok(if len < 1 << 7 {
+            1
+        } else if len < 1 << 14 {
+            2
+        } else {
+            3
+        })
+    }
+
+    #[inline(always)]
+    fn len_is_fixed_width<O: Options>() -> bool {
+        false
+    }
+
+    fn serialize_len<W: Write, O: Options>(
+        ser: &mut crate::Serializer<W, O>,
+        len: usize,
+    ) -> Result<()> {
+        let mut rest = cast_len(len, u16::MAX as u64)?;
+        loop {
+            let byte = (rest & 0x7f) as u8;
+            rest >>= 7;
+            if rest == 0 {
+                return ser.serialize_byte(byte);
+            }
+            ser.serialize_byte(byte | COMPACT_U16_MORE)?;
+        }
+    }
+
+    fn deserialize_len<'de, R: BincodeRead<'de>, O: Options>(
+        de: &mut crate::de::Deserializer<R, O>,
+    ) -> Result<usize> {
+        let mut len = 0;
+        for index in 0..COMPACT_U16_MAX_BYTES {
+            let byte = de.deserialize_byte()?;
+            // a zero byte after the first would only add leading zero bits to the length
+            if byte == 0 && index > 0 {
+                return Err(ErrorKind::InvalidLengthEncoding.into());
+            }
+            len |= ((byte & !COMPACT_U16_MORE) as usize) << (index * 7);
+            if byte & COMPACT_U16_MORE == 0 {
+                return cast_len(len, u16::MAX as u64).map(|len| len as usize);
+            }
+        }
+        // the third byte holds the top two bits, so it never continues
+        Err(ErrorKind::InvalidLengthEncoding.into())
+    }
+}
+
+const COMPACT_U16_MORE: u8 = 0x80;
+const COMPACT_U16_MAX_BYTES: usize = 3;
+
+fn cast_len(len: usize, max: u64) -> Result<u64> {
+    if len as u64 <= max {
+        Ok(len as u64)
+    } else {
+        Err(ErrorKind::LengthOverflow {
+            len: len as u64,
+            max,
+        }
+        .into())
+    }
+}
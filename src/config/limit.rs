@@ -1,5 +1,4 @@
 use crate::error::{ErrorKind, Result};
-use alloc::boxed::Box;
 
 /// A trait for stopping serialization and deserialization when a certain limit has been reached.
 pub trait SizeLimit {
@@ -27,7 +26,7 @@ impl SizeLimit for Bounded {
             self.0 -= n;
             Ok(())
         } else {
-            Err(Box::new(ErrorKind::SizeLimit))
+            Err(ErrorKind::SizeLimit.into())
         }
     }
 
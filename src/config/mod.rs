@@ -12,10 +12,22 @@
 //! Due to historical reasons, the default options used by the `serialize()` and `deserialize()`
 //! family of functions are different than the default options created by the `DefaultOptions` struct:
 //!
-//! |          | Byte limit | Endianness | Int Encoding | Trailing Behavior |
-//! |----------|------------|------------|--------------|-------------------|
-//! | struct   | Unlimited  | Little     | Varint       | Reject            |
-//! | function | Unlimited  | Little     | Fixint       | Allow             |
+//! | Option                   | struct                 | function               |
+//! |--------------------------|------------------------|------------------------|
+//! | Byte limit               | Unlimited              | Unlimited              |
+//! | Endianness               | Little                 | Little                 |
+//! | Int Encoding             | Varint                 | Fixint                 |
+//! | Trailing Behavior        | Reject                 | Allow                  |
+//! | Unknown Length Behavior  | Reject                 | Reject                 |
+//! | Length Encoding          | u64, int encoding      | u64, int encoding      |
+//! | Enum Tag                 | u32, int encoding      | u32, int encoding      |
+//! | Allocation Limit         | Unlimited              | Unlimited              |
+//! | Depth Limit              | Unlimited              | Unlimited              |
+//! | Collection Length Limit  | Unlimited              | Unlimited              |
+//! | Self-Describing          | Disabled               | Disabled               |
+//! | Struct Evolution         | Disabled               | Disabled               |
+//! | Variant Evolution        | Disabled               | Disabled               |
+//! | Error Context            | Disabled               | Disabled               |
 //!
 //! This means that if you want to use the `Serialize` / `Deserialize` structs with the same
 //! settings as the functions, you should adjust the `DefaultOptions` struct like so:
@@ -29,28 +41,54 @@
 
 use crate::de::read::BincodeRead;
 use crate::error::Result;
-use serde;
+#[cfg(feature = "alloc")]
+use crate::io::Read;
+use crate::io::{Seek, Write};
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
+#[cfg(feature = "futures-io")]
+use core::future::Future;
 use core::marker::PhantomData;
-use core2::io::{Read, Write};
+use serde;
 
 pub(crate) use self::endian::BincodeByteOrder;
+pub(crate) use self::enum_tag::EnumTag;
+pub(crate) use self::error_context::ErrorContext;
+pub(crate) use self::evolution::{StructEvolution, VariantEvolution};
 pub(crate) use self::int::IntEncoding;
+pub(crate) use self::int::{U16_BYTE, U32_BYTE, U64_BYTE};
 pub(crate) use self::internal::*;
+pub(crate) use self::len::LenEncoding;
 pub(crate) use self::limit::SizeLimit;
+pub(crate) use self::self_describing::SelfDescribing;
 pub(crate) use self::trailing::TrailingBytes;
+pub(crate) use self::unknown_length::UnknownLength;
 
 pub use self::endian::{BigEndian, LittleEndian, NativeEndian};
+pub use self::enum_tag::IntEncodingTag;
+pub use self::error_context::{NoErrorContext, TrackErrorContext};
+pub use self::evolution::{
+    FixedStructs, FixedVariants, LengthPrefixedStructs, LengthPrefixedVariants,
+};
 pub use self::int::{FixintEncoding, VarintEncoding};
 pub use self::legacy::*;
+pub use self::len::{CompactU16, FixU16, FixU32, FixU64, FixU8, IntEncodingLen, Varint};
 pub use self::limit::{Bounded, Infinite};
+pub use self::self_describing::{NoTypeTags, TypeTags};
 pub use self::trailing::{AllowTrailing, RejectTrailing};
+pub use self::unknown_length::{AllowUnknownLength, RejectUnknownLength};
 
 mod endian;
+mod enum_tag;
+mod error_context;
+mod evolution;
 mod int;
 mod legacy;
+mod len;
 mod limit;
+mod self_describing;
 mod trailing;
+mod unknown_length;
 
 /// The default options for bincode serialization/deserialization.
 ///
@@ -101,11 +139,35 @@ impl InternalOptions for DefaultOptions {
     type Endian = LittleEndian;
     type IntEncoding = VarintEncoding;
     type Trailing = RejectTrailing;
+    type UnknownLength = RejectUnknownLength;
+    type ErrorContext = NoErrorContext;
+    type SelfDescribing = NoTypeTags;
+    type LenEncoding = IntEncodingLen;
+    type EnumTag = IntEncodingTag;
+    type StructEvolution = FixedStructs;
+    type VariantEvolution = FixedVariants;
+    type AllocLimit = Infinite;
+    type DepthLimit = Infinite;
+    type CollectionLimit = Infinite;
 
     #[inline(always)]
     fn limit(&mut self) -> &mut Infinite {
         &mut self.0
     }
+
+    #[inline(always)]
+    fn collection_limit(&mut self) -> &mut Infinite {
+        &mut self.0
+    }
+
+    #[inline(always)]
+    fn depth_limit(&mut self) -> &mut Infinite {
+        &mut self.0
+    }
+
+    fn alloc_limit(&mut self) -> &mut Infinite {
+        &mut self.0
+    }
 }
 
 /// A configuration builder trait whose options Bincode will use
@@ -120,12 +182,47 @@ impl InternalOptions for DefaultOptions {
 ///
 /// Trailing Behavior: The behavior when there are trailing bytes left over in a slice after deserialization. *default: reject*
 ///
+/// Unknown Length Behavior: The behavior when serializing a sequence or map whose length is not known ahead of time. *default: reject*
+///
+/// Error Context: Whether deserialization errors report the byte offset and path at which they occurred. *default: disabled*
+///
+/// Self-Describing: Whether each value is preceded by a tag describing its type, which is required for `deserialize_any`. *default: disabled*
+///
+/// Length Encoding: The encoding of the length prefix of sequences, maps, strings and byte arrays. *default: the int encoding, as a u64*
+///
+/// Enum Tag: The encoding of the index of an enum variant. *default: the int encoding, as a u32*
+///
+/// Struct Evolution: Whether the fields of a struct are prefixed by their length in bytes, so that fields can be appended to it later. *default: disabled*
+///
+/// Variant Evolution: Whether the payload of an enum variant is prefixed by its length in bytes, so that variants can be appended to an enum later. *default: disabled*
+///
+/// Allocation Limit: The maximum number of bytes the deserializer will allocate for strings, byte buffers and collections. *default: unlimited*
+///
+/// Depth Limit: The maximum nesting depth of options, enums, sequences, maps, tuples and structs. *default: unlimited*
+///
+/// Collection Limit: The maximum length of a sequence, map, string or byte array that will be deserialized. *default: unlimited*
+///
 /// ### Byte Limit Details
 /// The purpose of byte-limiting is to prevent Denial-Of-Service attacks whereby malicious attackers get bincode
 /// deserialization to crash your process by allocating too much memory or keeping a connection open for too long.
 ///
 /// When a byte limit is set, bincode will return `Err` on any deserialization that goes over the limit, or any
 /// serialization that goes over the limit.
+///
+/// ### Allocation Limit Details
+/// A byte limit does not apply when deserializing from a slice, and a small input can still declare
+/// a huge length. The allocation limit bounds the memory that the deserializer itself allocates:
+/// every string and byte buffer is charged its length before it is allocated, and sequences and
+/// maps no longer report their declared length as a size hint, so collections only grow as their
+/// elements are actually read. Each sequence element, map key and map value is charged its inline
+/// size (`size_of`) as it is read.
+///
+/// The limit does not cover the spare capacity that a growing collection keeps, which can reach
+/// the size of its elements again, nor the per-entry overhead of maps and sets, nor allocations
+/// made by `Deserialize` impls outside of bincode, such as the boxes of `Box<T>`.
+///
+/// When the total would go over the limit, deserialization returns `ErrorKind::AllocationLimit`
+/// before anything is allocated.
 pub trait Options: InternalOptions + Sized {
     /// Sets the byte limit to be unlimited.
     /// This is the default.
@@ -164,6 +261,80 @@ pub trait Options: InternalOptions + Sized {
         WithOtherIntEncoding::new(self)
     }
 
+    /// Sets the encoding of length prefixes, independently of the int encoding.
+    ///
+    /// By default lengths are encoded as a u64 with the int encoding. `FixU8`, `FixU16`, `FixU32`
+    /// and `FixU64` encode them as fixed-size integers, `Varint` as a varint, and `CompactU16`
+    /// in the `short_vec` format used by Solana. Serializing a length that does not fit in the
+    /// chosen encoding returns `ErrorKind::LengthOverflow`.
+    fn with_len_encoding<L: LenEncoding>(self) -> WithOtherLenEncoding<Self, L> {
+        WithOtherLenEncoding::new(self)
+    }
+
+    /// Sets the length encoding to compact-u16, the `short_vec` format used by Solana.
+    ///
+    /// This is a shorthand for `with_len_encoding::<CompactU16>()`, so integers keep the int
+    /// encoding whether it is set before or after this.
+    fn with_compact_u16_lengths(self) -> WithOtherLenEncoding<Self, CompactU16> {
+        WithOtherLenEncoding::new(self)
+    }
+
+    /// Sets the encoding of the index of an enum variant.
+    ///
+    /// By default the index is encoded as a u32 with the int encoding. `u8`, `u16` and `u32`
+    /// encode it as a fixed-size integer and `Varint` as a varint. Serializing a variant whose
+    /// index does not fit in the tag returns `ErrorKind::EnumTagOverflow`.
+    fn with_enum_tag<T: EnumTag>(self) -> WithOtherEnumTag<Self, T> {
+        WithOtherEnumTag::new(self)
+    }
+
+    /// Sets the serializer to write the fields of a struct one after another, like a tuple.
+    /// This is the default.
+    fn without_struct_evolution(self) -> WithOtherStructEvolution<Self, FixedStructs> {
+        WithOtherStructEvolution::new(self)
+    }
+
+    /// Sets the serializer to write the length in bytes of the fields of each struct and struct
+    /// variant before them, and the deserializer to expect it.
+    ///
+    /// Data written this way can still be read after fields are appended to a struct: fields
+    /// that follow the ones a reader knows are skipped, and fields that the data ends before are
+    /// filled in with their `#[serde(default)]`. Fields must only ever be appended, never
+    /// removed or reordered, and tuple structs are not affected.
+    ///
+    /// Unless the writer is seekable and the length encoding has a fixed width, the fields of
+    /// every struct are buffered to find their length, which requires the `alloc` feature.
+    /// Self-describing data already names every field, so it is not changed by this option.
+    fn with_struct_evolution(self) -> WithOtherStructEvolution<Self, LengthPrefixedStructs> {
+        WithOtherStructEvolution::new(self)
+    }
+
+    /// Sets the serializer to write the payload of an enum variant right after its index.
+    /// This is the default.
+    fn without_variant_evolution(self) -> WithOtherVariantEvolution<Self, FixedVariants> {
+        WithOtherVariantEvolution::new(self)
+    }
+
+    /// Sets the serializer to write the length in bytes of the payload of each enum variant
+    /// after its index, and the deserializer to expect it.
+    ///
+    /// Data written this way can still be read by a reader that does not know some of its
+    /// variants, as long as variants are only ever appended to an enum. A variant index the
+    /// enum does not have is read as the enum's `#[serde(other)]` unit variant, and its payload
+    /// is skipped. Without one, an unknown variant is still an error.
+    ///
+    /// Reading an enum `E` as a [`de::OrUnknown<E>`](../de/enum.OrUnknown.html) instead keeps
+    /// the index and the payload of an unknown variant, and writes them back out unchanged. It
+    /// requires the `alloc` feature.
+    ///
+    /// Since the payload of a struct variant is delimited by its length, fields can also be
+    /// appended to struct variants, as with `with_struct_evolution`. Payloads are buffered or
+    /// backpatched the same way as the fields of a struct, and self-describing data is not
+    /// changed by this option.
+    fn with_variant_evolution(self) -> WithOtherVariantEvolution<Self, LengthPrefixedVariants> {
+        WithOtherVariantEvolution::new(self)
+    }
+
     /// Sets the deserializer to reject trailing bytes
     fn reject_trailing_bytes(self) -> WithOtherTrailing<Self, RejectTrailing> {
         WithOtherTrailing::new(self)
@@ -174,7 +345,101 @@ pub trait Options: InternalOptions + Sized {
         WithOtherTrailing::new(self)
     }
 
+    /// Sets the serializer to reject sequences and maps of unknown length
+    /// This is the default.
+    fn reject_unknown_lengths(self) -> WithOtherUnknownLength<Self, RejectUnknownLength> {
+        WithOtherUnknownLength::new(self)
+    }
+
+    /// Sets the serializer to accept sequences and maps of unknown length, such as those
+    /// produced by `#[serde(flatten)]` or `Serializer::collect_seq` over a filtered iterator.
+    ///
+    /// The length prefix is backpatched when serializing with `serialize_into_seekable` and the
+    /// length encoding has a fixed width; otherwise the elements are buffered in memory until
+    /// the sequence ends. Either way the output is identical to that of a sequence of known length.
+    fn allow_unknown_lengths(self) -> WithOtherUnknownLength<Self, AllowUnknownLength> {
+        WithOtherUnknownLength::new(self)
+    }
+
+    /// Sets the deserializer to return errors without their location
+    /// This is the default.
+    fn without_error_context(self) -> WithOtherErrorContext<Self, NoErrorContext> {
+        WithOtherErrorContext::new(self)
+    }
+
+    /// Sets the deserializer to wrap errors in an `ErrorKind::Context` that records the byte
+    /// offset and the path of the value being deserialized when the error occurred, such as
+    /// `Block.transactions[12].signatures[0]`.
+    ///
+    /// Tracking the path has a small cost for every nested value, so it is disabled by default.
+    fn with_error_context(self) -> WithOtherErrorContext<Self, TrackErrorContext> {
+        WithOtherErrorContext::new(self)
+    }
+
+    /// Sets the serializer to write values without type tags
+    /// This is the default.
+    fn without_self_describing(self) -> WithOtherSelfDescribing<Self, NoTypeTags> {
+        WithOtherSelfDescribing::new(self)
+    }
+
+    /// Sets the serializer to write a one byte tag describing the type of each value, and the
+    /// deserializer to expect one.
+    ///
+    /// This makes the format self-describing, so `Deserializer::deserialize_any` is supported and
+    /// types such as `#[serde(untagged)]` and internally tagged enums can be deserialized. Structs
+    /// are written as maps keyed by their field names, which makes the output larger.
+    fn with_self_describing(self) -> WithOtherSelfDescribing<Self, TypeTags> {
+        WithOtherSelfDescribing::new(self)
+    }
+
+    /// Removes the allocation limit.
+    /// This is the default.
+    fn with_no_alloc_limit(self) -> WithOtherAllocLimit<Self, Infinite> {
+        WithOtherAllocLimit::new(self, Infinite)
+    }
+
+    /// Limits the number of bytes the deserializer may allocate to `limit`.
+    ///
+    /// Unlike the byte limit, this also applies to `deserialize` from a slice, and it bounds
+    /// the memory that length prefixes can make the deserializer reserve up front. See
+    /// [Allocation Limit Details](#allocation-limit-details).
+    fn with_alloc_limit(self, limit: u64) -> WithOtherAllocLimit<Self, Bounded> {
+        WithOtherAllocLimit::new(self, Bounded(limit))
+    }
+
+    /// Removes the limit on how deeply values may be nested.
+    /// This is the default.
+    fn with_no_max_depth(self) -> WithOtherDepthLimit<Self, Infinite> {
+        WithOtherDepthLimit::new(self, Infinite)
+    }
+
+    /// Limits how deeply values may be nested to `depth`.
+    ///
+    /// Each option, enum, sequence, map, tuple and struct is one level deeper than the value
+    /// containing it. Going past `depth` while serializing or deserializing returns
+    /// `ErrorKind::DepthLimitExceeded`, which keeps recursive types in untrusted input from
+    /// overflowing the stack.
+    fn with_max_depth(self, depth: u64) -> WithOtherDepthLimit<Self, Bounded> {
+        WithOtherDepthLimit::new(self, Bounded(depth))
+    }
+
+    /// Removes the limit on the length of deserialized collections.
+    /// This is the default.
+    fn with_no_max_collection_len(self) -> WithOtherCollectionLimit<Self, Infinite> {
+        WithOtherCollectionLimit::new(self, Infinite)
+    }
+
+    /// Limits the length of every sequence, map, string and byte array that is deserialized to
+    /// `len` elements (or bytes).
+    ///
+    /// A longer length prefix returns `ErrorKind::CollectionLengthLimit` as soon as it is read,
+    /// before any of the elements.
+    fn with_max_collection_len(self, len: u64) -> WithOtherCollectionLimit<Self, Bounded> {
+        WithOtherCollectionLimit::new(self, Bounded(len))
+    }
+
     /// Serializes a serializable object into a `Vec` of bytes using this configuration
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     fn serialize<S: ?Sized + serde::Serialize>(self, t: &S) -> Result<Vec<u8>> {
         crate::internal::serialize(t, self)
@@ -195,12 +460,135 @@ pub trait Options: InternalOptions + Sized {
         crate::internal::serialize_into(w, t, self)
     }
 
+    /// Serializes an object directly into a slice using this configuration, returning the number
+    /// of bytes written
+    ///
+    /// Nothing is allocated, except to buffer sequences and maps of unknown length, and the size
+    /// of the object is only computed ahead of time when a size limit is set. If the object does
+    /// not fit, `ErrorKind::BufferTooSmall` reports the number of bytes it needs, and the
+    /// contents of the slice are unspecified.
+    #[inline(always)]
+    fn serialize_into_slice<T: ?Sized + serde::Serialize>(
+        self,
+        slice: &mut [u8],
+        t: &T,
+    ) -> Result<usize> {
+        crate::internal::serialize_into_slice(slice, t, self)
+    }
+
+    /// Serializes an object directly into a seekable `Writer` using this configuration
+    ///
+    /// This behaves like `serialize_into`, except that the lengths of sequences and maps of
+    /// unknown length, and of structs with struct evolution, are backpatched in the writer
+    /// instead of being buffered, when possible.
+    #[inline(always)]
+    fn serialize_into_seekable<W: Write + Seek, T: ?Sized + serde::Serialize>(
+        self,
+        w: W,
+        t: &T,
+    ) -> Result<()> {
+        crate::internal::serialize_into_seekable(w, t, self)
+    }
+
+    /// Serializes an object into a `Writer` as a frame using this configuration
+    ///
+    /// The frame starts with the length of the serialized object, encoded as set by `framing`,
+    /// and may end with a checksum. See the [frame](../frame/index.html) module for the layout.
+    /// If the object is larger than the size limit, nothing is written.
+    #[inline(always)]
+    fn serialize_framed<W: Write, T: ?Sized + serde::Serialize>(
+        self,
+        framing: crate::frame::Framing,
+        w: W,
+        t: &T,
+    ) -> Result<()> {
+        crate::frame::serialize_framed(framing, w, t, self)
+    }
+
+    /// Serializes an object into a `Writer` behind an envelope using this configuration
+    ///
+    /// The envelope records the magic number and schema version of `envelope` and a fingerprint
+    /// of this configuration, which `deserialize_enveloped` checks. See the
+    /// [envelope](../envelope/index.html) module for what the fingerprint covers.
+    #[inline(always)]
+    fn serialize_enveloped<W: Write, T: ?Sized + serde::Serialize>(
+        self,
+        envelope: crate::envelope::Envelope,
+        w: W,
+        t: &T,
+    ) -> Result<()> {
+        crate::envelope::serialize_enveloped(envelope, w, t, self)
+    }
+
+    /// Serializes an object directly into an `embedded_io::Write`r using this configuration
+    ///
+    /// This behaves like `serialize_into`.
+    #[cfg(feature = "embedded-io")]
+    #[inline(always)]
+    fn serialize_into_embedded<W, T>(self, w: W, t: &T) -> Result<()>
+    where
+        W: embedded_io::Write,
+        T: ?Sized + serde::Serialize,
+    {
+        crate::internal::serialize_into(crate::embedded::EmbeddedIo(w), t, self)
+    }
+
+    /// Serializes an object into a `bytes::BufMut` using this configuration
+    ///
+    /// If the buffer can not grow and the object does not fit, an error is returned and the
+    /// buffer holds the part of the object that did.
+    #[cfg(feature = "bytes")]
+    #[inline(always)]
+    fn serialize_into_buf<B, T>(self, buf: &mut B, t: &T) -> Result<()>
+    where
+        B: bytes::BufMut,
+        T: ?Sized + serde::Serialize,
+    {
+        crate::internal::serialize_into(crate::buf::BufWriter(buf), t, self)
+    }
+
+    /// Serializes an object into a `futures_io::AsyncWrite`r using this configuration
+    ///
+    /// The object is serialized into a buffer first, so that nothing is written if it exceeds
+    /// the size limit.
+    #[cfg(feature = "futures-io")]
+    #[inline(always)]
+    fn serialize_into_async<W, T>(self, w: W, t: &T) -> impl Future<Output = Result<()>>
+    where
+        W: futures_io::AsyncWrite + Unpin,
+        T: ?Sized + serde::Serialize,
+    {
+        crate::async_io::serialize_into(w, t, self)
+    }
+
     /// Deserializes a slice of bytes into an instance of `T` using this configuration
     #[inline(always)]
     fn deserialize<'a, T: serde::Deserialize<'a>>(self, bytes: &'a [u8]) -> Result<T> {
         crate::internal::deserialize(bytes, self)
     }
 
+    /// Deserializes an instance of `T` from the start of a slice of bytes using this
+    /// configuration, returning it along with the bytes that follow it
+    ///
+    /// Trailing bytes are never rejected, so several concatenated objects can be deserialized
+    /// from one slice:
+    ///
+    /// ```
+    /// # use bincode::{DefaultOptions, Options};
+    /// let mut bytes = DefaultOptions::new().serialize("first").unwrap();
+    /// bytes.extend(DefaultOptions::new().serialize(&2u32).unwrap());
+    /// let (first, rest): (String, _) = DefaultOptions::new().deserialize_partial(&bytes).unwrap();
+    /// let (second, rest): (u32, _) = DefaultOptions::new().deserialize_partial(rest).unwrap();
+    /// assert_eq!((first.as_str(), second, rest.len()), ("first", 2, 0));
+    /// ```
+    #[inline(always)]
+    fn deserialize_partial<'a, T: serde::Deserialize<'a>>(
+        self,
+        bytes: &'a [u8],
+    ) -> Result<(T, &'a [u8])> {
+        crate::internal::deserialize_partial(bytes, self)
+    }
+
     /// TODO: document
     #[doc(hidden)]
     #[inline(always)]
@@ -224,15 +612,144 @@ pub trait Options: InternalOptions + Sized {
 
     /// Deserializes an object directly from a `Read`er using this configuration
     ///
+    /// Strings and byte arrays are copied out of the reader. A `BufRead`er can instead be wrapped
+    /// in a [`BufReadReader`](../de/read/struct.BufReadReader.html) and passed to
+    /// `deserialize_from_custom`, which borrows them from its buffer where it can.
+    ///
     /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     fn deserialize_from<R: Read, T: serde::de::DeserializeOwned>(self, reader: R) -> Result<T> {
         crate::internal::deserialize_from(reader, self)
     }
 
+    /// Returns an iterator over the objects stored back to back in a `Read`er, deserialized
+    /// using this configuration
+    ///
+    /// The iterator ends cleanly when the reader ends between two objects, and yields an error
+    /// if it ends in the middle of one. A size limit applies to every object separately.
+    ///
+    /// ```
+    /// # use bincode::{DefaultOptions, Options};
+    /// let options = DefaultOptions::new().with_limit(16);
+    /// let mut log = Vec::new();
+    /// for record in &["first", "second", "third"] {
+    ///     options.serialize_into(&mut log, record).unwrap();
+    /// }
+    /// let records: Vec<String> = options
+    ///     .deserialize_stream(&log[..])
+    ///     .collect::<bincode::Result<_>>()
+    ///     .unwrap();
+    /// assert_eq!(records, ["first", "second", "third"]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    fn deserialize_stream<T, R>(self, reader: R) -> crate::de::StreamDeserializer<R, T, Self>
+    where
+        Self: Clone,
+        T: serde::de::DeserializeOwned,
+        R: Read,
+    {
+        crate::de::StreamDeserializer::new(reader, self)
+    }
+
+    /// Deserializes a slice of bytes written by `serialize_enveloped` into an instance of `T`
+    /// using this configuration
+    ///
+    /// Returns `ErrorKind::InvalidMagic`, `ErrorKind::FingerprintMismatch` or
+    /// `ErrorKind::SchemaVersionMismatch` without deserializing anything if the envelope does
+    /// not match `envelope` and this configuration.
+    #[inline(always)]
+    fn deserialize_enveloped<'a, T: serde::Deserialize<'a>>(
+        self,
+        envelope: crate::envelope::Envelope,
+        bytes: &'a [u8],
+    ) -> Result<T> {
+        crate::envelope::deserialize_enveloped(envelope, bytes, self)
+    }
+
+    /// Deserializes an object from a frame written by `serialize_framed` using this
+    /// configuration
+    ///
+    /// The whole payload is read and its checksum verified before the object is deserialized
+    /// from it, and a mismatch returns `ErrorKind::ChecksumMismatch`. A payload longer than the
+    /// size limit is rejected before it is read.
+    ///
+    /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    fn deserialize_framed<R: Read, T: serde::de::DeserializeOwned>(
+        self,
+        framing: crate::frame::Framing,
+        reader: R,
+    ) -> Result<T> {
+        crate::frame::deserialize_framed(framing, reader, self)
+    }
+
+    /// Deserializes an object directly from an `embedded_io::Read`er using this configuration
+    ///
+    /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[cfg(feature = "embedded-io")]
+    #[inline(always)]
+    fn deserialize_from_embedded<R, T>(self, reader: R) -> Result<T>
+    where
+        R: embedded_io::Read,
+        T: serde::de::DeserializeOwned,
+    {
+        let reader = crate::embedded::EmbeddedIoReader::new(reader);
+        crate::internal::deserialize_from_custom(reader, self)
+    }
+
+    /// Deserializes an object from a `bytes::Buf` using this configuration
+    ///
+    /// `buf` is advanced past the object, so consecutive objects can be deserialized from it.
+    /// If this returns an `Error`, `buf` may be in an invalid state.
+    #[cfg(feature = "bytes")]
+    #[inline(always)]
+    fn deserialize_from_buf<B, T>(self, buf: &mut B) -> Result<T>
+    where
+        B: bytes::Buf,
+        T: serde::de::DeserializeOwned,
+    {
+        let reader = crate::buf::BufReader::new(buf);
+        crate::internal::deserialize_from_custom(reader, self)
+    }
+
+    /// Deserializes a `bytes::Bytes` into an instance of `T` using this configuration
+    ///
+    /// This behaves like `deserialize`, and in addition `bytes::Bytes` fields that are
+    /// deserialized [`shared`](../buf/shared/index.html) share the memory of `bytes` instead of
+    /// being copied.
+    #[cfg(feature = "bytes")]
+    #[inline(always)]
+    fn deserialize_from_bytes<'a, T: serde::Deserialize<'a>>(
+        self,
+        bytes: &'a bytes::Bytes,
+    ) -> Result<T> {
+        crate::buf::with_source(bytes, || crate::internal::deserialize(bytes, self))
+    }
+
+    /// Deserializes an object directly from a `futures_io::AsyncRead`er using this configuration
+    ///
+    /// Only the bytes of the object are read, so objects can be read back to back from the same
+    /// reader. See the [async_io](../async_io/index.html) module for how the object is read.
+    ///
+    /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[cfg(feature = "futures-io")]
+    #[inline(always)]
+    fn deserialize_from_async<R, T>(self, reader: R) -> impl Future<Output = Result<T>>
+    where
+        Self: Clone,
+        R: futures_io::AsyncRead + Unpin,
+        T: serde::de::DeserializeOwned,
+    {
+        crate::async_io::deserialize_from(reader, self)
+    }
+
     /// Deserializes an object directly from a `Read`er with state `seed` using this configuration
     ///
     /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     fn deserialize_from_seed<'a, R: Read, T: serde::de::DeserializeSeed<'a>>(
         self,
@@ -275,7 +792,7 @@ impl<T: InternalOptions> Options for T {}
 /// A configuration struct with a user-specified byte limit
 #[derive(Clone, Copy)]
 pub struct WithOtherLimit<O: Options, L: SizeLimit> {
-    _options: O,
+    options: O,
     pub(crate) new_limit: L,
 }
 
@@ -300,11 +817,81 @@ pub struct WithOtherTrailing<O: Options, T: TrailingBytes> {
     _trailing: PhantomData<T>,
 }
 
+/// A configuration struct with a user-specified unknown length behavior.
+#[derive(Clone, Copy)]
+pub struct WithOtherUnknownLength<O: Options, U: UnknownLength> {
+    options: O,
+    _unknown_length: PhantomData<U>,
+}
+
+/// A configuration struct with a user-specified error context behavior.
+#[derive(Clone, Copy)]
+pub struct WithOtherErrorContext<O: Options, C: ErrorContext> {
+    options: O,
+    _error_context: PhantomData<C>,
+}
+
+/// A configuration struct with a user-specified self-describing behavior.
+#[derive(Clone, Copy)]
+pub struct WithOtherSelfDescribing<O: Options, S: SelfDescribing> {
+    options: O,
+    _self_describing: PhantomData<S>,
+}
+
+/// A configuration struct with a user-specified length encoding.
+#[derive(Clone, Copy)]
+pub struct WithOtherLenEncoding<O: Options, L: LenEncoding> {
+    options: O,
+    _len_encoding: PhantomData<L>,
+}
+
+/// A configuration struct with a user-specified enum tag encoding.
+#[derive(Clone, Copy)]
+pub struct WithOtherEnumTag<O: Options, T: EnumTag> {
+    options: O,
+    _enum_tag: PhantomData<T>,
+}
+
+/// A configuration struct with a user-specified struct evolution behavior.
+#[derive(Clone, Copy)]
+pub struct WithOtherStructEvolution<O: Options, S: StructEvolution> {
+    options: O,
+    _struct_evolution: PhantomData<S>,
+}
+
+/// A configuration struct with a user-specified variant evolution behavior.
+#[derive(Clone, Copy)]
+pub struct WithOtherVariantEvolution<O: Options, V: VariantEvolution> {
+    options: O,
+    _variant_evolution: PhantomData<V>,
+}
+
+/// A configuration struct with a user-specified allocation limit
+#[derive(Clone, Copy)]
+pub struct WithOtherAllocLimit<O: Options, A: SizeLimit> {
+    options: O,
+    pub(crate) alloc_limit: A,
+}
+
+/// A configuration struct with a user-specified maximum nesting depth
+#[derive(Clone, Copy)]
+pub struct WithOtherDepthLimit<O: Options, D: SizeLimit> {
+    options: O,
+    pub(crate) depth_limit: D,
+}
+
+/// A configuration struct with a user-specified maximum collection length
+#[derive(Clone, Copy)]
+pub struct WithOtherCollectionLimit<O: Options, C: SizeLimit> {
+    options: O,
+    pub(crate) collection_limit: C,
+}
+
 impl<O: Options, L: SizeLimit> WithOtherLimit<O, L> {
     #[inline(always)]
     pub(crate) fn new(options: O, limit: L) -> WithOtherLimit<O, L> {
         WithOtherLimit {
-            _options: options,
+            options,
             new_limit: limit,
         }
     }
@@ -340,15 +927,139 @@ impl<O: Options, T: TrailingBytes> WithOtherTrailing<O, T> {
     }
 }
 
+impl<O: Options, U: UnknownLength> WithOtherUnknownLength<O, U> {
+    #[inline(always)]
+    pub(crate) fn new(options: O) -> WithOtherUnknownLength<O, U> {
+        WithOtherUnknownLength {
+            options,
+            _unknown_length: PhantomData,
+        }
+    }
+}
+
+impl<O: Options, C: ErrorContext> WithOtherErrorContext<O, C> {
+    #[inline(always)]
+    pub(crate) fn new(options: O) -> WithOtherErrorContext<O, C> {
+        WithOtherErrorContext {
+            options,
+            _error_context: PhantomData,
+        }
+    }
+}
+
+impl<O: Options, S: SelfDescribing> WithOtherSelfDescribing<O, S> {
+    #[inline(always)]
+    pub(crate) fn new(options: O) -> WithOtherSelfDescribing<O, S> {
+        WithOtherSelfDescribing {
+            options,
+            _self_describing: PhantomData,
+        }
+    }
+}
+
+impl<O: Options, L: LenEncoding> WithOtherLenEncoding<O, L> {
+    #[inline(always)]
+    pub(crate) fn new(options: O) -> WithOtherLenEncoding<O, L> {
+        WithOtherLenEncoding {
+            options,
+            _len_encoding: PhantomData,
+        }
+    }
+}
+
+impl<O: Options, T: EnumTag> WithOtherEnumTag<O, T> {
+    #[inline(always)]
+    pub(crate) fn new(options: O) -> WithOtherEnumTag<O, T> {
+        WithOtherEnumTag {
+            options,
+            _enum_tag: PhantomData,
+        }
+    }
+}
+
+impl<O: Options, S: StructEvolution> WithOtherStructEvolution<O, S> {
+    #[inline(always)]
+    pub(crate) fn new(options: O) -> WithOtherStructEvolution<O, S> {
+        WithOtherStructEvolution {
+            options,
+            _struct_evolution: PhantomData,
+        }
+    }
+}
+
+impl<O: Options, V: VariantEvolution> WithOtherVariantEvolution<O, V> {
+    #[inline(always)]
+    pub(crate) fn new(options: O) -> WithOtherVariantEvolution<O, V> {
+        WithOtherVariantEvolution {
+            options,
+            _variant_evolution: PhantomData,
+        }
+    }
+}
+
+impl<O: Options, A: SizeLimit> WithOtherAllocLimit<O, A> {
+    #[inline(always)]
+    pub(crate) fn new(options: O, alloc_limit: A) -> WithOtherAllocLimit<O, A> {
+        WithOtherAllocLimit {
+            options,
+            alloc_limit,
+        }
+    }
+}
+
+impl<O: Options, D: SizeLimit> WithOtherDepthLimit<O, D> {
+    #[inline(always)]
+    pub(crate) fn new(options: O, depth_limit: D) -> WithOtherDepthLimit<O, D> {
+        WithOtherDepthLimit {
+            options,
+            depth_limit,
+        }
+    }
+}
+
+impl<O: Options, C: SizeLimit> WithOtherCollectionLimit<O, C> {
+    #[inline(always)]
+    pub(crate) fn new(options: O, collection_limit: C) -> WithOtherCollectionLimit<O, C> {
+        WithOtherCollectionLimit {
+            options,
+            collection_limit,
+        }
+    }
+}
+
 impl<O: Options, E: BincodeByteOrder + 'static> InternalOptions for WithOtherEndian<O, E> {
     type Limit = O::Limit;
     type Endian = E;
     type IntEncoding = O::IntEncoding;
     type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
     #[inline(always)]
     fn limit(&mut self) -> &mut O::Limit {
         self.options.limit()
     }
+
+    #[inline(always)]
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    #[inline(always)]
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
 }
 
 impl<O: Options, L: SizeLimit + 'static> InternalOptions for WithOtherLimit<O, L> {
@@ -356,9 +1067,31 @@ impl<O: Options, L: SizeLimit + 'static> InternalOptions for WithOtherLimit<O, L
     type Endian = O::Endian;
     type IntEncoding = O::IntEncoding;
     type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
     fn limit(&mut self) -> &mut L {
         &mut self.new_limit
     }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
 }
 
 impl<O: Options, I: IntEncoding + 'static> InternalOptions for WithOtherIntEncoding<O, I> {
@@ -366,10 +1099,32 @@ impl<O: Options, I: IntEncoding + 'static> InternalOptions for WithOtherIntEncod
     type Endian = O::Endian;
     type IntEncoding = I;
     type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
 
     fn limit(&mut self) -> &mut O::Limit {
         self.options.limit()
     }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
 }
 
 impl<O: Options, T: TrailingBytes + 'static> InternalOptions for WithOtherTrailing<O, T> {
@@ -377,10 +1132,364 @@ impl<O: Options, T: TrailingBytes + 'static> InternalOptions for WithOtherTraili
     type Endian = O::Endian;
     type IntEncoding = O::IntEncoding;
     type Trailing = T;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+}
+
+impl<O: Options, U: UnknownLength + 'static> InternalOptions for WithOtherUnknownLength<O, U> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = U;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+}
+
+impl<O: Options, C: ErrorContext + 'static> InternalOptions for WithOtherErrorContext<O, C> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = C;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+}
+
+impl<O: Options, S: SelfDescribing + 'static> InternalOptions for WithOtherSelfDescribing<O, S> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = S;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+}
+
+impl<O: Options, L: LenEncoding + 'static> InternalOptions for WithOtherLenEncoding<O, L> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = L;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+}
+
+impl<O: Options, T: EnumTag + 'static> InternalOptions for WithOtherEnumTag<O, T> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = T;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+}
+
+impl<O: Options, S: StructEvolution + 'static> InternalOptions for WithOtherStructEvolution<O, S> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = S;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+}
+
+impl<O: Options, V: VariantEvolution + 'static> InternalOptions
+    for WithOtherVariantEvolution<O, V>
+{
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = V;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+}
+
+impl<O: Options, A: SizeLimit + 'static> InternalOptions for WithOtherAllocLimit<O, A> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = A;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut A {
+        &mut self.alloc_limit
+    }
+}
+
+impl<O: Options, D: SizeLimit + 'static> InternalOptions for WithOtherDepthLimit<O, D> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = D;
+    type CollectionLimit = O::CollectionLimit;
+
+    fn limit(&mut self) -> &mut O::Limit {
+        self.options.limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut O::CollectionLimit {
+        self.options.collection_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+
+    fn depth_limit(&mut self) -> &mut D {
+        &mut self.depth_limit
+    }
+}
+
+impl<O: Options, C: SizeLimit + 'static> InternalOptions for WithOtherCollectionLimit<O, C> {
+    type Limit = O::Limit;
+    type Endian = O::Endian;
+    type IntEncoding = O::IntEncoding;
+    type Trailing = O::Trailing;
+    type UnknownLength = O::UnknownLength;
+    type ErrorContext = O::ErrorContext;
+    type SelfDescribing = O::SelfDescribing;
+    type LenEncoding = O::LenEncoding;
+    type EnumTag = O::EnumTag;
+    type StructEvolution = O::StructEvolution;
+    type VariantEvolution = O::VariantEvolution;
+    type AllocLimit = O::AllocLimit;
+    type DepthLimit = O::DepthLimit;
+    type CollectionLimit = C;
 
     fn limit(&mut self) -> &mut O::Limit {
         self.options.limit()
     }
+
+    fn depth_limit(&mut self) -> &mut O::DepthLimit {
+        self.options.depth_limit()
+    }
+
+    fn alloc_limit(&mut self) -> &mut O::AllocLimit {
+        self.options.alloc_limit()
+    }
+
+    fn collection_limit(&mut self) -> &mut C {
+        &mut self.collection_limit
+    }
 }
 
 mod internal {
@@ -391,8 +1500,21 @@ mod internal {
         type Endian: BincodeByteOrder + 'static;
         type IntEncoding: IntEncoding + 'static;
         type Trailing: TrailingBytes + 'static;
+        type UnknownLength: UnknownLength + 'static;
+        type ErrorContext: ErrorContext + 'static;
+        type SelfDescribing: SelfDescribing + 'static;
+        type LenEncoding: LenEncoding + 'static;
+        type EnumTag: EnumTag + 'static;
+        type StructEvolution: StructEvolution + 'static;
+        type VariantEvolution: VariantEvolution + 'static;
+        type AllocLimit: SizeLimit + 'static;
+        type DepthLimit: SizeLimit + 'static;
+        type CollectionLimit: SizeLimit + 'static;
 
         fn limit(&mut self) -> &mut Self::Limit;
+        fn collection_limit(&mut self) -> &mut Self::CollectionLimit;
+        fn depth_limit(&mut self) -> &mut Self::DepthLimit;
+        fn alloc_limit(&mut self) -> &mut Self::AllocLimit;
     }
 
     impl<'a, O: InternalOptions> InternalOptions for &'a mut O {
@@ -400,10 +1522,35 @@ mod internal {
         type Endian = O::Endian;
         type IntEncoding = O::IntEncoding;
         type Trailing = O::Trailing;
+        type UnknownLength = O::UnknownLength;
+        type ErrorContext = O::ErrorContext;
+        type SelfDescribing = O::SelfDescribing;
+        type LenEncoding = O::LenEncoding;
+        type EnumTag = O::EnumTag;
+        type StructEvolution = O::StructEvolution;
+        type VariantEvolution = O::VariantEvolution;
+        type AllocLimit = O::AllocLimit;
+        type DepthLimit = O::DepthLimit;
+        type CollectionLimit = O::CollectionLimit;
 
         #[inline(always)]
         fn limit(&mut self) -> &mut Self::Limit {
             (*self).limit()
         }
+
+        #[inline(always)]
+        fn collection_limit(&mut self) -> &mut Self::CollectionLimit {
+            (*self).collection_limit()
+        }
+
+        #[inline(always)]
+        fn depth_limit(&mut self) -> &mut Self::DepthLimit {
+            (*self).depth_limit()
+        }
+
+        #[inline(always)]
+        fn alloc_limit(&mut self) -> &mut Self::AllocLimit {
+            (*self).alloc_limit()
+        }
     }
 }
@@ -0,0 +1,33 @@
+/// A trait for deciding whether every value is preceded by a tag describing its type.
+pub trait SelfDescribing {
+    /// Returns true if the serializer should write a type tag before each value, which lets the
+    /// deserializer support `deserialize_any`, `deserialize_identifier` and
+    /// `deserialize_ignored_any`.
+    fn enabled() -> bool;
+}
+
+/// A SelfDescribing config that writes values without type tags, as bincode always has.
+#[derive(Copy, Clone)]
+pub struct NoTypeTags;
+
+/// A SelfDescribing config that writes a one byte type tag before each value.
+///
+/// Structs are written as maps keyed by their field names and enum variants carry their name
+/// next to their index, so that the data can be read back without knowing its schema, for
+/// example into an `#[serde(untagged)]` enum or a dynamically typed value.
+#[derive(Copy, Clone)]
+pub struct TypeTags;
+
+impl SelfDescribing for NoTypeTags {
+    #[inline(always)]
+    fn enabled() -> bool {
+        false
+    }
+}
+
+impl SelfDescribing for TypeTags {
+    #[inline(always)]
+    fn enabled() -> bool {
+        true
+    }
+}
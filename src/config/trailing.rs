@@ -1,4 +1,3 @@
-use alloc::{boxed::Box, string::ToString};
 use crate::de::read::SliceReader;
 use crate::{ErrorKind, Result};
 
@@ -30,9 +29,7 @@ impl TrailingBytes for RejectTrailing {
         if reader.is_finished() {
             Ok(())
         } else {
-            Err(Box::new(ErrorKind::Custom(
-                "Slice had bytes remaining after deserialization".to_string(),
-            )))
+            Err(ErrorKind::Custom("Slice had bytes remaining after deserialization".into()).into())
         }
     }
 }
@@ -0,0 +1,32 @@
+/// A trait for deciding whether sequences and maps without a known length can be serialized.
+pub trait UnknownLength {
+    /// Returns true if a sequence or map whose length is not known up front should be
+    /// serialized by writing its length once all of its elements have been written.
+    fn allowed() -> bool;
+}
+
+/// An UnknownLength config that will cause bincode to produce an error when a sequence or map
+/// does not report its length ahead of time.
+#[derive(Copy, Clone)]
+pub struct RejectUnknownLength;
+
+/// An UnknownLength config that will serialize sequences and maps of unknown length.
+///
+/// The length prefix is backpatched in place when the writer is seekable and lengths have a
+/// fixed width; otherwise the elements are buffered until the sequence ends.
+#[derive(Copy, Clone)]
+pub struct AllowUnknownLength;
+
+impl UnknownLength for RejectUnknownLength {
+    #[inline(always)]
+    fn allowed() -> bool {
+        false
+    }
+}
+
+impl UnknownLength for AllowUnknownLength {
+    #[inline(always)]
+    fn allowed() -> bool {
+        true
+    }
+}
@@ -1,17 +1,40 @@
-use alloc::{vec::Vec, string::String, boxed::Box};
 use crate::config::{BincodeByteOrder, Options};
-use core2::io::Read;
-
-use self::read::{BincodeRead, IoReader, SliceReader};
+use crate::io::Read;
+#[cfg(feature = "alloc")]
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "alloc")]
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use self::read::IoReader;
+use self::read::{BincodeRead, SliceReader};
 use crate::byteorder::ReadBytesExt;
-use crate::config::{IntEncoding, SizeLimit};
+#[cfg(feature = "alloc")]
+use crate::config::ErrorContext;
+use crate::config::{
+    EnumTag, IntEncoding, LenEncoding, SelfDescribing, SizeLimit, StructEvolution, VariantEvolution,
+};
+use crate::tag::Tag;
+use crate::{Error, ErrorKind, Result};
 use serde;
 use serde::de::Error as DeError;
 use serde::de::IntoDeserializer;
-use crate::{Error, ErrorKind, Result};
 
 /// Specialized ways to read data into bincode.
 pub mod read;
+#[cfg(feature = "alloc")]
+mod stream;
+#[cfg(feature = "alloc")]
+mod unknown;
+
+#[cfg(feature = "alloc")]
+pub use self::stream::StreamDeserializer;
+#[cfg(feature = "alloc")]
+pub use self::unknown::{OrUnknown, UnknownVariant};
 
 /// A Deserializer that reads bytes from a buffer.
 ///
@@ -21,14 +44,84 @@ pub mod read;
 /// The ByteOrder that is chosen will impact the endianness that
 /// is used to read integers out of the reader.
 ///
-/// ```ignore
-/// let d = Deserializer::new(&mut some_reader, SizeLimit::new());
-/// serde::Deserialize::deserialize(&mut deserializer);
-/// let bytes_read = d.bytes_read();
+/// ```
+/// # use bincode::{de::Deserializer, DefaultOptions, Options};
+/// let bytes = DefaultOptions::new().serialize(&(1u8, "two")).unwrap();
+/// let mut deserializer = Deserializer::from_slice(&bytes, DefaultOptions::new());
+/// let value: u8 = serde::Deserialize::deserialize(&mut deserializer).unwrap();
+/// assert_eq!(value, 1);
+/// assert_eq!(deserializer.bytes_read(), 1);
+/// assert_eq!(deserializer.remaining(), &bytes[1..]);
 /// ```
 pub struct Deserializer<R, O: Options> {
     pub(crate) reader: R,
     options: O,
+    bytes_read: u64,
+    /// The offset of the most recent read, reported as the location of an error.
+    read_start: u64,
+    /// The path to the value being deserialized; only tracked with error context enabled.
+    #[cfg(feature = "alloc")]
+    path: Vec<PathSegment>,
+    /// The number of options, enums and collections enclosing the value being deserialized.
+    depth: u64,
+}
+
+/// One step of the path to a value, used to describe where an error occurred.
+#[derive(Clone, Copy)]
+#[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+enum PathSegment {
+    /// The name of the outermost struct or enum.
+    Root(&'static str),
+    Field(&'static str),
+    TupleField(usize),
+    Index(usize),
+    Variant(&'static str),
+    /// A variant index that the type being deserialized does not know about.
+    UnknownVariant(u32),
+}
+
+#[cfg(feature = "alloc")]
+struct Path<'a>(&'a [PathSegment]);
+
+#[cfg(feature = "alloc")]
+impl<'a> fmt::Display for Path<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for segment in self.0 {
+            match *segment {
+                PathSegment::Root(name) => fmt.write_str(name)?,
+                PathSegment::Field(name) => write!(fmt, ".{}", name)?,
+                PathSegment::TupleField(index) => write!(fmt, ".{}", index)?,
+                PathSegment::Index(index) => write!(fmt, "[{}]", index)?,
+                PathSegment::Variant(name) => write!(fmt, "::{}", name)?,
+                PathSegment::UnknownVariant(index) => write!(fmt, "::{}", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The size of the buffer that the rest of a length-prefixed value is skipped through.
+const SKIP_CHUNK: usize = 256;
+
+/// How the elements visited by `Deserializer::deserialize_elements` are named in a path.
+#[derive(Clone, Copy)]
+enum Elements {
+    Seq,
+    Tuple,
+    Fields(&'static [&'static str]),
+}
+
+impl Elements {
+    fn segment(self, index: usize) -> PathSegment {
+        match self {
+            Elements::Seq => PathSegment::Index(index),
+            Elements::Tuple => PathSegment::TupleField(index),
+            Elements::Fields(fields) => match fields.get(index) {
+                Some(name) => PathSegment::Field(name),
+                None => PathSegment::TupleField(index),
+            },
+        }
+    }
 }
 
 macro_rules! impl_deserialize_literal {
@@ -43,30 +136,122 @@ macro_rules! impl_deserialize_literal {
     };
 }
 
+#[cfg(feature = "alloc")]
 impl<'de, IR: Read, O: Options> Deserializer<IoReader<IR>, O> {
     /// Creates a new Deserializer with a given `Read`er and options.
     pub fn with_reader(r: IR, options: O) -> Self {
-        Deserializer {
-            reader: IoReader::new(r),
-            options,
-        }
+        Deserializer::with_bincode_read(IoReader::new(r), options)
     }
 }
 
 impl<'de, O: Options> Deserializer<SliceReader<'de>, O> {
     /// Creates a new Deserializer that will read from the given slice.
     pub fn from_slice(slice: &'de [u8], options: O) -> Self {
-        Deserializer {
-            reader: SliceReader::new(slice),
-            options,
-        }
+        Deserializer::with_bincode_read(SliceReader::new(slice), options)
+    }
+
+    /// Returns the bytes of the slice that have not been read yet.
+    pub fn remaining(&self) -> &'de [u8] {
+        self.reader.remaining()
     }
 }
 
 impl<'de, R: BincodeRead<'de>, O: Options> Deserializer<R, O> {
     /// Creates a new Deserializer with the given `BincodeRead`er
     pub fn with_bincode_read(r: R, options: O) -> Deserializer<R, O> {
-        Deserializer { reader: r, options }
+        Deserializer {
+            reader: r,
+            options,
+            bytes_read: 0,
+            read_start: 0,
+            #[cfg(feature = "alloc")]
+            path: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Wraps `error` in an `ErrorKind::Context` describing where it occurred, if error context
+    /// is enabled and the error does not already carry one.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn with_error_context(&mut self, error: Error) -> Error {
+        if !O::ErrorContext::enabled() {
+            return error;
+        }
+        if let ErrorKind::Context { .. } = *error {
+            return error;
+        }
+
+        let path = Path(&self.path).to_string();
+        self.path.clear();
+        Box::new(ErrorKind::Context {
+            offset: self.read_start,
+            path,
+            source: error,
+        })
+    }
+
+    /// Without the `alloc` feature there is nowhere to put the context, so `error` is returned
+    /// unchanged.
+    #[cfg(not(feature = "alloc"))]
+    pub(crate) fn with_error_context(&mut self, error: Error) -> Error {
+        error
+    }
+
+    #[inline(always)]
+    fn enter(&mut self, segment: PathSegment) {
+        #[cfg(feature = "alloc")]
+        if O::ErrorContext::enabled() {
+            self.path.push(segment);
+        }
+        #[cfg(not(feature = "alloc"))]
+        let _ = segment;
+    }
+
+    /// Leaves the segment added by the matching `enter`. This is only called once the nested
+    /// value has been deserialized, so that an error leaves the path to the failing value behind.
+    #[inline(always)]
+    fn leave(&mut self) {
+        #[cfg(feature = "alloc")]
+        if O::ErrorContext::enabled() {
+            self.path.pop();
+        }
+    }
+
+    /// Returns true if nothing has been entered yet, i.e. the value being deserialized is the
+    /// outermost one. Always true without the `alloc` feature, which does not track the path.
+    #[inline(always)]
+    fn at_root(&self) -> bool {
+        #[cfg(feature = "alloc")]
+        return self.path.is_empty();
+        #[cfg(not(feature = "alloc"))]
+        true
+    }
+
+    /// Runs `f` one level deeper, returning an error if that is deeper than the depth limit.
+    #[inline(always)]
+    fn nested<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        self.depth += 1;
+        let result = match self.options.depth_limit().limit() {
+            Some(max) if self.depth > max => Err(ErrorKind::DepthLimitExceeded.into()),
+            _ => f(self),
+        };
+        self.depth -= 1;
+        result
+    }
+
+    /// Records that `count` bytes are about to be read.
+    #[inline(always)]
+    fn advance(&mut self, count: u64) {
+        self.read_start = self.bytes_read;
+        self.bytes_read += count;
     }
 
     pub(crate) fn deserialize_byte(&mut self) -> Result<u8> {
@@ -78,12 +263,76 @@ impl<'de, R: BincodeRead<'de>, O: Options> Deserializer<R, O> {
     impl_deserialize_literal! { deserialize_literal_u32 : u32 = read_u32() }
     impl_deserialize_literal! { deserialize_literal_u64 : u64 = read_u64() }
 
+    impl_deserialize_literal! { deserialize_literal_f32 : f32 = read_f32() }
+    impl_deserialize_literal! { deserialize_literal_f64 : f64 = read_f64() }
+
     serde_if_integer128! {
         impl_deserialize_literal! { deserialize_literal_u128 : u128 = read_u128() }
     }
 
+    fn deserialize_tag(&mut self) -> Result<Tag> {
+        let tag = self.deserialize_byte()?;
+        Tag::from_u8(tag).ok_or_else(|| ErrorKind::InvalidTagEncoding(tag as usize).into())
+    }
+
+    /// Reads the index of an enum variant, along with the tag and name around it if the format
+    /// is self-describing.
+    fn deserialize_variant_index(&mut self) -> Result<u32> {
+        if !O::SelfDescribing::enabled() {
+            return O::EnumTag::deserialize_tag(self);
+        }
+
+        match self.deserialize_tag()? {
+            Tag::Enum => {}
+            tag => return Err(ErrorKind::InvalidTagEncoding(tag as usize).into()),
+        }
+        let idx = O::EnumTag::deserialize_tag(self)?;
+        let len = self.read_forwarded_len()?;
+        self.reader.forward_read_str(len, serde::de::IgnoredAny)?;
+        Ok(idx)
+    }
+
+    fn read_bool(&mut self) -> Result<bool> {
+        match self.deserialize_byte()? {
+            1 => Ok(true),
+            0 => Ok(false),
+            value => Err(ErrorKind::InvalidBoolEncoding(value).into()),
+        }
+    }
+
+    fn read_char(&mut self) -> Result<char> {
+        use core::str;
+
+        let error = || ErrorKind::InvalidCharEncoding.into();
+
+        let mut buf = [0u8; 4];
+
+        // Look at the first byte to see how many bytes must be read
+        self.advance(1);
+        self.reader.read_exact(&mut buf[..1])?;
+        let width = utf8_char_width(buf[0]);
+        if width == 1 {
+            return Ok(buf[0] as char);
+        }
+        if width == 0 {
+            return Err(error());
+        }
+
+        self.bytes_read += (width - 1) as u64;
+        if self.reader.read_exact(&mut buf[1..width]).is_err() {
+            return Err(error());
+        }
+
+        str::from_utf8(&buf[..width])
+            .ok()
+            .and_then(|s| s.chars().next())
+            .ok_or_else(error)
+    }
+
     fn read_bytes(&mut self, count: u64) -> Result<()> {
-        self.options.limit().add(count)
+        self.options.limit().add(count)?;
+        self.advance(count);
+        Ok(())
     }
 
     fn read_literal_type<T>(&mut self) -> Result<()> {
@@ -91,18 +340,83 @@ impl<'de, R: BincodeRead<'de>, O: Options> Deserializer<R, O> {
         self.read_bytes(size_of::<T>() as u64)
     }
 
+    /// Reads the length of a sequence, map, string or byte array, checking it against the
+    /// collection length limit.
+    fn deserialize_len(&mut self) -> Result<usize> {
+        let len = O::LenEncoding::deserialize_len(self)?;
+        match self.options.collection_limit().limit() {
+            Some(max) if len as u64 > max => Err(ErrorKind::CollectionLengthLimit {
+                len: len as u64,
+                max,
+            }
+            .into()),
+            _ => Ok(len),
+        }
+    }
+
+    /// Returns an error if allocating `n` more bytes would exceed the allocation limit.
+    fn check_alloc(&mut self, n: u64) -> Result<()> {
+        match self.options.alloc_limit().limit() {
+            Some(remaining) if n > remaining => Err(ErrorKind::AllocationLimit {
+                requested: n,
+                remaining,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Charges `n` bytes that are kept for the rest of deserialization to the allocation limit.
+    #[cfg(feature = "alloc")]
+    fn charge_alloc(&mut self, n: u64) -> Result<()> {
+        self.check_alloc(n)?;
+        self.options.alloc_limit().add(n)
+    }
+
+    /// Charges the inline size of a sequence element, map key or map value, which the
+    /// collection it is read into keeps for the rest of deserialization.
+    #[cfg(feature = "alloc")]
+    fn charge_element<T>(&mut self) -> Result<()> {
+        self.charge_alloc(core::mem::size_of::<T>() as u64)
+    }
+
+    /// Reads the length of a string or byte array that is about to be passed on by one of the
+    /// reader's forward_read_* methods. Readers that copy it into a reusable buffer only hold
+    /// `len` bytes at a time, so it is checked against the allocation limit without being charged.
+    fn read_forwarded_len(&mut self) -> Result<usize> {
+        let len = self.deserialize_len()?;
+        self.read_bytes(len as u64)?;
+        if self.reader.forward_reads_allocate() {
+            self.check_alloc(len as u64)?;
+        }
+        Ok(len)
+    }
+
+    #[cfg(feature = "alloc")]
     fn read_vec(&mut self) -> Result<Vec<u8>> {
-        let len = O::IntEncoding::deserialize_len(self)?;
+        let len = self.deserialize_len()?;
         self.read_bytes(len as u64)?;
+        self.charge_alloc(len as u64)?;
         self.reader.get_byte_buffer(len)
     }
 
+    #[cfg(feature = "alloc")]
     fn read_string(&mut self) -> Result<String> {
         let vec = self.read_vec()?;
         String::from_utf8(vec).map_err(|e| ErrorKind::InvalidUtf8Encoding(e.utf8_error()).into())
     }
 }
 
+/// Forwards to `deserialize_any` if the format is self-describing, since the tag in front of
+/// the value then decides how it is read.
+macro_rules! forward_self_describing {
+    ($self:ident, $visitor:ident) => {
+        if O::SelfDescribing::enabled() {
+            return $self.deserialize_any($visitor);
+        }
+    };
+}
+
 macro_rules! impl_deserialize_int {
     ($name:ident = $visitor_method:ident ($dser_method:ident)) => {
         #[inline]
@@ -110,6 +424,7 @@ macro_rules! impl_deserialize_int {
         where
             V: serde::de::Visitor<'de>,
         {
+            forward_self_describing!(self, visitor);
             visitor.$visitor_method(O::IntEncoding::$dser_method(self)?)
         }
     };
@@ -122,23 +437,66 @@ where
 {
     type Error = Error;
 
-    #[inline]
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        Err(Box::new(ErrorKind::DeserializeAnyNotSupported))
+        if !O::SelfDescribing::enabled() {
+            return Err(ErrorKind::DeserializeAnyNotSupported.into());
+        }
+
+        match self.deserialize_tag()? {
+            Tag::Unit => visitor.visit_unit(),
+            Tag::Bool => visitor.visit_bool(self.read_bool()?),
+            Tag::U8 => visitor.visit_u8(self.deserialize_byte()?),
+            Tag::U16 => visitor.visit_u16(O::IntEncoding::deserialize_u16(self)?),
+            Tag::U32 => visitor.visit_u32(O::IntEncoding::deserialize_u32(self)?),
+            Tag::U64 => visitor.visit_u64(O::IntEncoding::deserialize_u64(self)?),
+            Tag::U128 => visitor.visit_u128(O::IntEncoding::deserialize_u128(self)?),
+            Tag::I8 => visitor.visit_i8(self.deserialize_byte()? as i8),
+            Tag::I16 => visitor.visit_i16(O::IntEncoding::deserialize_i16(self)?),
+            Tag::I32 => visitor.visit_i32(O::IntEncoding::deserialize_i32(self)?),
+            Tag::I64 => visitor.visit_i64(O::IntEncoding::deserialize_i64(self)?),
+            Tag::I128 => visitor.visit_i128(O::IntEncoding::deserialize_i128(self)?),
+            Tag::F32 => visitor.visit_f32(self.deserialize_literal_f32()?),
+            Tag::F64 => visitor.visit_f64(self.deserialize_literal_f64()?),
+            Tag::Char => visitor.visit_char(self.read_char()?),
+            Tag::Str => {
+                let len = self.read_forwarded_len()?;
+                self.reader.forward_read_str(len, visitor)
+            }
+            Tag::Bytes => {
+                let len = self.read_forwarded_len()?;
+                self.reader.forward_read_bytes(len, visitor)
+            }
+            Tag::None => visitor.visit_none(),
+            Tag::Some => self.nested(|de| visitor.visit_some(de)),
+            Tag::Seq => {
+                let len = self.deserialize_len()?;
+                self.deserialize_elements(len, None, Elements::Seq, visitor)
+            }
+            Tag::Map => {
+                let len = self.deserialize_len()?;
+                self.deserialize_entries(len, visitor)
+            }
+            Tag::Enum => {
+                O::EnumTag::deserialize_tag(self)?;
+                self.nested(|de| {
+                    visitor.visit_map(VariantMap {
+                        deserializer: de,
+                        variant: true,
+                    })
+                })
+            }
+        }
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        match self.deserialize_byte()? {
-            1 => visitor.visit_bool(true),
-            0 => visitor.visit_bool(false),
-            value => Err(ErrorKind::InvalidBoolEncoding(value).into()),
-        }
+        forward_self_describing!(self, visitor);
+        visitor.visit_bool(self.read_bool()?)
     }
 
     impl_deserialize_int!(deserialize_u16 = visit_u16(deserialize_u16));
@@ -152,22 +510,16 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        self.read_literal_type::<f32>()?;
-        let value = self
-            .reader
-            .read_f32::<<O::Endian as BincodeByteOrder>::Endian>()?;
-        visitor.visit_f32(value)
+        forward_self_describing!(self, visitor);
+        visitor.visit_f32(self.deserialize_literal_f32()?)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        self.read_literal_type::<f64>()?;
-        let value = self
-            .reader
-            .read_f64::<<O::Endian as BincodeByteOrder>::Endian>()?;
-        visitor.visit_f64(value)
+        forward_self_describing!(self, visitor);
+        visitor.visit_f64(self.deserialize_literal_f64()?)
     }
 
     serde_if_integer128! {
@@ -180,6 +532,7 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
+        forward_self_describing!(self, visitor);
         visitor.visit_u8(self.deserialize_byte()? as u8)
     }
 
@@ -188,6 +541,7 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
+        forward_self_describing!(self, visitor);
         visitor.visit_i8(self.deserialize_byte()? as i8)
     }
 
@@ -195,6 +549,7 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
+        forward_self_describing!(self, visitor);
         visitor.visit_unit()
     }
 
@@ -202,39 +557,16 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        use core::str;
-
-        let error = || ErrorKind::InvalidCharEncoding.into();
-
-        let mut buf = [0u8; 4];
-
-        // Look at the first byte to see how many bytes must be read
-        self.reader.read_exact(&mut buf[..1])?;
-        let width = utf8_char_width(buf[0]);
-        if width == 1 {
-            return visitor.visit_char(buf[0] as char);
-        }
-        if width == 0 {
-            return Err(error());
-        }
-
-        if self.reader.read_exact(&mut buf[1..width]).is_err() {
-            return Err(error());
-        }
-
-        let res = str::from_utf8(&buf[..width])
-            .ok()
-            .and_then(|s| s.chars().next())
-            .ok_or_else(error)?;
-        visitor.visit_char(res)
+        forward_self_describing!(self, visitor);
+        visitor.visit_char(self.read_char()?)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        let len = O::IntEncoding::deserialize_len(self)?;
-        self.read_bytes(len as u64)?;
+        forward_self_describing!(self, visitor);
+        let len = self.read_forwarded_len()?;
         self.reader.forward_read_str(len, visitor)
     }
 
@@ -242,15 +574,21 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_string(self.read_string()?)
+        #[cfg(not(feature = "alloc"))]
+        return self.deserialize_str(visitor);
+        #[cfg(feature = "alloc")]
+        {
+            forward_self_describing!(self, visitor);
+            visitor.visit_string(self.read_string()?)
+        }
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        let len = O::IntEncoding::deserialize_len(self)?;
-        self.read_bytes(len as u64)?;
+        forward_self_describing!(self, visitor);
+        let len = self.read_forwarded_len()?;
         self.reader.forward_read_bytes(len, visitor)
     }
 
@@ -258,86 +596,59 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_byte_buf(self.read_vec()?)
+        #[cfg(not(feature = "alloc"))]
+        return self.deserialize_bytes(visitor);
+        #[cfg(feature = "alloc")]
+        {
+            forward_self_describing!(self, visitor);
+            visitor.visit_byte_buf(self.read_vec()?)
+        }
     }
 
     fn deserialize_enum<V>(
         self,
-        _enum: &'static str,
-        _variants: &'static [&'static str],
+        name: &'static str,
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        impl<'de, 'a, R: 'a, O> serde::de::EnumAccess<'de> for &'a mut Deserializer<R, O>
-        where
-            R: BincodeRead<'de>,
-            O: Options,
-        {
-            type Error = Error;
-            type Variant = Self;
-
-            fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
-            where
-                V: serde::de::DeserializeSeed<'de>,
-            {
-                let idx: u32 = O::IntEncoding::deserialize_u32(self)?;
-                let val: Result<_> = seed.deserialize(idx.into_deserializer());
-                Ok((val?, self))
-            }
+        let root = self.at_root();
+        if root {
+            self.enter(PathSegment::Root(name));
         }
-
-        visitor.visit_enum(self)
+        let value = self.nested(|de| {
+            visitor.visit_enum(EnumAccess {
+                deserializer: de,
+                variants,
+            })
+        })?;
+        // leave the variant
+        self.leave();
+        if root {
+            self.leave();
+        }
+        Ok(value)
     }
 
     fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        struct Access<'a, R: Read + 'a, O: Options + 'a> {
-            deserializer: &'a mut Deserializer<R, O>,
-            len: usize,
-        }
-
-        impl<'de, 'a, 'b: 'a, R: BincodeRead<'de> + 'b, O: Options> serde::de::SeqAccess<'de>
-            for Access<'a, R, O>
-        {
-            type Error = Error;
-
-            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
-            where
-                T: serde::de::DeserializeSeed<'de>,
-            {
-                if self.len > 0 {
-                    self.len -= 1;
-                    let value =
-                        serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
-                    Ok(Some(value))
-                } else {
-                    Ok(None)
-                }
-            }
-
-            fn size_hint(&self) -> Option<usize> {
-                Some(self.len)
-            }
-        }
-
-        visitor.visit_seq(Access {
-            deserializer: self,
-            len,
-        })
+        forward_self_describing!(self, visitor);
+        self.deserialize_elements(len, None, Elements::Tuple, visitor)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
+        forward_self_describing!(self, visitor);
         let value: u8 = serde::de::Deserialize::deserialize(&mut *self)?;
         match value {
             0 => visitor.visit_none(),
-            1 => visitor.visit_some(&mut *self),
+            1 => self.nested(|de| visitor.visit_some(de)),
             v => Err(ErrorKind::InvalidTagEncoding(v as usize).into()),
         }
     }
@@ -346,76 +657,51 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let len = O::IntEncoding::deserialize_len(self)?;
+        forward_self_describing!(self, visitor);
+        let len = self.deserialize_len()?;
 
-        self.deserialize_tuple(len, visitor)
+        self.deserialize_elements(len, None, Elements::Seq, visitor)
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        struct Access<'a, R: Read + 'a, O: Options + 'a> {
-            deserializer: &'a mut Deserializer<R, O>,
-            len: usize,
-        }
-
-        impl<'de, 'a, 'b: 'a, R: BincodeRead<'de> + 'b, O: Options> serde::de::MapAccess<'de>
-            for Access<'a, R, O>
-        {
-            type Error = Error;
-
-            fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
-            where
-                K: serde::de::DeserializeSeed<'de>,
-            {
-                if self.len > 0 {
-                    self.len -= 1;
-                    let key =
-                        serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
-                    Ok(Some(key))
-                } else {
-                    Ok(None)
-                }
-            }
-
-            fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
-            where
-                V: serde::de::DeserializeSeed<'de>,
-            {
-                let value = serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
-                Ok(value)
-            }
-
-            fn size_hint(&self) -> Option<usize> {
-                Some(self.len)
-            }
-        }
+        forward_self_describing!(self, visitor);
+        let len = self.deserialize_len()?;
 
-        let len = O::IntEncoding::deserialize_len(self)?;
-
-        visitor.visit_map(Access {
-            deserializer: self,
-            len,
-        })
+        self.deserialize_entries(len, visitor)
     }
 
     fn deserialize_struct<V>(
         self,
-        _name: &str,
+        name: &'static str,
         fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        self.deserialize_tuple(fields.len(), visitor)
+        let root = self.at_root();
+        if root {
+            self.enter(PathSegment::Root(name));
+        }
+        let value = if O::SelfDescribing::enabled() {
+            self.deserialize_any(visitor)?
+        } else {
+            self.deserialize_fields(fields, visitor)?
+        };
+        if root {
+            self.leave();
+        }
+        Ok(value)
     }
 
-    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
+        forward_self_describing!(self, visitor);
         let message = "Bincode does not support Deserializer::deserialize_identifier";
         Err(Error::custom(message))
     }
@@ -431,6 +717,7 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
+        forward_self_describing!(self, visitor);
         visitor.visit_unit()
     }
 
@@ -446,10 +733,11 @@ where
         self.deserialize_tuple(len, visitor)
     }
 
-    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
+        forward_self_describing!(self, visitor);
         let message = "Bincode does not support Deserializer::deserialize_ignored_any";
         Err(Error::custom(message))
     }
@@ -467,6 +755,9 @@ where
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
+        if O::SelfDescribing::enabled() {
+            return serde::de::Deserialize::deserialize(self);
+        }
         Ok(())
     }
 
@@ -481,14 +772,400 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        serde::de::Deserializer::deserialize_tuple(self, len, visitor)
+        if O::SelfDescribing::enabled() {
+            return serde::Deserializer::deserialize_any(self, visitor);
+        }
+        self.deserialize_elements(len, None, Elements::Tuple, visitor)
     }
 
     fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        serde::de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+        if O::SelfDescribing::enabled() {
+            return serde::Deserializer::deserialize_any(self, visitor);
+        }
+        self.deserialize_fields(fields, visitor)
+    }
+}
+
+struct EnumAccess<'a, R: 'a, O: Options + 'a> {
+    deserializer: &'a mut Deserializer<R, O>,
+    variants: &'static [&'static str],
+}
+
+impl<'de, 'a, R: 'a, O> serde::de::EnumAccess<'de> for EnumAccess<'a, R, O>
+where
+    R: BincodeRead<'de>,
+    O: Options,
+{
+    type Error = Error;
+    type Variant = Payload<'a, R, O>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let idx: u32 = self.deserializer.deserialize_variant_index()?;
+        let end = if O::VariantEvolution::enabled() && !O::SelfDescribing::enabled() {
+            Some(self.deserializer.deserialize_byte_len()?)
+        } else {
+            None
+        };
+
+        let segment = match self.variants.get(idx as usize) {
+            Some(name) => PathSegment::Variant(name),
+            None => PathSegment::UnknownVariant(idx),
+        };
+        self.deserializer.enter(segment);
+        let val: Result<_> = seed.deserialize(idx.into_deserializer());
+        let payload = Payload {
+            deserializer: self.deserializer,
+            end,
+        };
+        Ok((val?, payload))
+    }
+}
+
+/// The payload of an enum variant, which is read up to the byte offset `end` if it is prefixed
+/// by its length.
+struct Payload<'a, R: 'a, O: Options + 'a> {
+    deserializer: &'a mut Deserializer<R, O>,
+    end: Option<u64>,
+}
+
+impl<'de, 'a, R, O> Payload<'a, R, O>
+where
+    R: BincodeRead<'de>,
+    O: Options,
+{
+    fn finish<T>(self, value: T) -> Result<T> {
+        if let Some(end) = self.end {
+            self.deserializer.skip_to(end)?;
+        }
+        Ok(value)
+    }
+}
+
+impl<'de, 'a, R, O> serde::de::VariantAccess<'de> for Payload<'a, R, O>
+where
+    R: BincodeRead<'de>,
+    O: Options,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        if self.end.is_none() {
+            return serde::de::VariantAccess::unit_variant(self.deserializer);
+        }
+        // the payload of a variant read as a `#[serde(other)]` variant is skipped
+        self.finish(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        let value = serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
+        self.finish(value)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.end {
+            Some(end) => {
+                let value = self.deserializer.deserialize_elements(
+                    len,
+                    Some(end),
+                    Elements::Tuple,
+                    visitor,
+                )?;
+                self.finish(value)
+            }
+            None => serde::de::VariantAccess::tuple_variant(self.deserializer, len, visitor),
+        }
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.end {
+            // the length of the variant already delimits its fields
+            Some(end) => {
+                let elements = Elements::Fields(fields);
+                let value = self.deserializer.deserialize_elements(
+                    fields.len(),
+                    Some(end),
+                    elements,
+                    visitor,
+                )?;
+                self.finish(value)
+            }
+            None => serde::de::VariantAccess::struct_variant(self.deserializer, fields, visitor),
+        }
+    }
+}
+
+/// An enum read by `deserialize_any`, which is presented as a map with a single entry from the
+/// variant name to its content.
+struct VariantMap<'a, R: 'a, O: Options + 'a> {
+    deserializer: &'a mut Deserializer<R, O>,
+    /// True until the variant name has been read.
+    variant: bool,
+}
+
+/// The name of the variant of an enum read by `deserialize_any`, which is passed on to the
+/// visitor the same way as a string, without allocating.
+struct VariantName<'a, R: 'a, O: Options + 'a>(&'a mut Deserializer<R, O>);
+
+impl<'de, 'a, R: 'a, O> serde::Deserializer<'de> for VariantName<'a, R, O>
+where
+    R: BincodeRead<'de>,
+    O: Options,
+{
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let len = self.0.read_forwarded_len()?;
+        self.0.reader.forward_read_str(len, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, 'a, R: 'a, O> serde::de::MapAccess<'de> for VariantMap<'a, R, O>
+where
+    R: BincodeRead<'de>,
+    O: Options,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        if !core::mem::take(&mut self.variant) {
+            return Ok(None);
+        }
+        seed.deserialize(VariantName(&mut *self.deserializer))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.deserializer)
+    }
+}
+
+impl<'de, R: BincodeRead<'de>, O: Options> Deserializer<R, O> {
+    /// Visits `len` elements, or fewer if the byte offset `end` is reached first.
+    fn deserialize_elements<V>(
+        &mut self,
+        len: usize,
+        end: Option<u64>,
+        elements: Elements,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        struct Access<'a, R: Read + 'a, O: Options + 'a> {
+            deserializer: &'a mut Deserializer<R, O>,
+            len: usize,
+            end: Option<u64>,
+            index: usize,
+            reserve: bool,
+            elements: Elements,
+        }
+
+        impl<'de, 'a, 'b: 'a, R: BincodeRead<'de> + 'b, O: Options> serde::de::SeqAccess<'de>
+            for Access<'a, R, O>
+        {
+            type Error = Error;
+
+            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+            where
+                T: serde::de::DeserializeSeed<'de>,
+            {
+                let ended = match self.end {
+                    Some(end) => self.deserializer.bytes_read >= end,
+                    None => false,
+                };
+                if self.len > 0 && !ended {
+                    self.len -= 1;
+                    #[cfg(feature = "alloc")]
+                    if let Elements::Seq = self.elements {
+                        self.deserializer.charge_element::<T::Value>()?;
+                    }
+                    self.deserializer.enter(self.elements.segment(self.index));
+                    let value =
+                        serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
+                    self.deserializer.leave();
+                    self.index += 1;
+                    Ok(Some(value))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                if self.reserve {
+                    Some(self.len)
+                } else {
+                    None
+                }
+            }
+        }
+
+        // A declared length is not worth reserving for when allocations are limited.
+        let reserve = self.options.alloc_limit().limit().is_none();
+        self.nested(|de| {
+            visitor.visit_seq(Access {
+                deserializer: de,
+                len,
+                end,
+                index: 0,
+                elements,
+                reserve,
+            })
+        })
+    }
+
+    /// Visits the fields of a struct or struct variant, which are prefixed by their length in
+    /// bytes if struct evolution is enabled. Fields past the ones in `fields` are skipped.
+    fn deserialize_fields<V>(
+        &mut self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let elements = Elements::Fields(fields);
+        if !O::StructEvolution::enabled() {
+            return self.deserialize_elements(fields.len(), None, elements, visitor);
+        }
+        let end = self.deserialize_byte_len()?;
+        let value = self.deserialize_elements(fields.len(), Some(end), elements, visitor)?;
+        self.skip_to(end)?;
+        Ok(value)
+    }
+
+    /// Reads the length in bytes of the value that follows, returning the byte offset at which
+    /// it ends. A length past the byte limit is rejected before any of the value is read.
+    fn deserialize_byte_len(&mut self) -> Result<u64> {
+        let len = O::LenEncoding::deserialize_len(self)? as u64;
+        if let Some(remaining) = self.options.limit().limit() {
+            if len > remaining {
+                return Err(ErrorKind::SizeLimit.into());
+            }
+        }
+        Ok(self.bytes_read.saturating_add(len))
+    }
+
+    /// Skips the rest of a value that ends at the byte offset `end`.
+    fn skip_to(&mut self, end: u64) -> Result<()> {
+        if self.bytes_read > end {
+            return Err(custom_error!(
+                "a value read {} bytes past the end of its length prefix",
+                self.bytes_read - end
+            )
+            .into());
+        }
+        let mut rest = end - self.bytes_read;
+        self.read_bytes(rest)?;
+        if !self.reader.forward_reads_allocate() {
+            self.reader
+                .forward_read_bytes(rest as usize, serde::de::IgnoredAny)?;
+            return Ok(());
+        }
+        // readers that would copy the bytes into a buffer of their own are read in chunks
+        let mut scratch = [0u8; SKIP_CHUNK];
+        while rest > 0 {
+            let n = rest.min(SKIP_CHUNK as u64) as usize;
+            self.reader.read_exact(&mut scratch[..n])?;
+            rest -= n as u64;
+        }
+        Ok(())
+    }
+
+    fn deserialize_entries<V>(&mut self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        struct Access<'a, R: Read + 'a, O: Options + 'a> {
+            deserializer: &'a mut Deserializer<R, O>,
+            len: usize,
+            index: usize,
+            reserve: bool,
+        }
+
+        impl<'de, 'a, 'b: 'a, R: BincodeRead<'de> + 'b, O: Options> serde::de::MapAccess<'de>
+            for Access<'a, R, O>
+        {
+            type Error = Error;
+
+            fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+            where
+                K: serde::de::DeserializeSeed<'de>,
+            {
+                if self.len > 0 {
+                    self.len -= 1;
+                    #[cfg(feature = "alloc")]
+                    self.deserializer.charge_element::<K::Value>()?;
+                    self.deserializer.enter(PathSegment::Index(self.index));
+                    let key =
+                        serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
+                    self.deserializer.leave();
+                    Ok(Some(key))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+            where
+                V: serde::de::DeserializeSeed<'de>,
+            {
+                #[cfg(feature = "alloc")]
+                self.deserializer.charge_element::<V::Value>()?;
+                self.deserializer.enter(PathSegment::Index(self.index));
+                let value = serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
+                self.deserializer.leave();
+                self.index += 1;
+                Ok(value)
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                if self.reserve {
+                    Some(self.len)
+                } else {
+                    None
+                }
+            }
+        }
+
+        let reserve = self.options.alloc_limit().limit().is_none();
+        self.nested(|de| {
+            visitor.visit_map(Access {
+                deserializer: de,
+                len,
+                index: 0,
+                reserve,
+            })
+        })
     }
 }
 static UTF8_CHAR_WIDTH: [u8; 256] = [
@@ -514,3 +1191,142 @@ static UTF8_CHAR_WIDTH: [u8; 256] = [
 fn utf8_char_width(b: u8) -> usize {
     UTF8_CHAR_WIDTH[b as usize] as usize
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{DefaultOptions, Error, ErrorKind, Options};
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Instruction<'a> {
+        Transfer {
+            lamports: u64,
+            memo: Option<&'a str>,
+        },
+        Assign(&'a [u8]),
+        Close,
+    }
+
+    fn kind(err: &Error) -> &ErrorKind {
+        err
+    }
+
+    fn round_trip<O: Options + Copy>(options: O, value: &Instruction) {
+        let mut buf = [0u8; 64];
+        let len = options.serialize_into_slice(&mut buf, value).unwrap();
+        assert_eq!(options.serialized_size(value).unwrap(), len as u64);
+        let decoded: Instruction = options.deserialize(&buf[..len]).unwrap();
+        assert_eq!(&decoded, value);
+    }
+
+    #[test]
+    fn test_borrowed_decode() {
+        let value: (&str, &[u8], u32) = ("hello", &[1, 2, 3], 7);
+        let mut buf = [0u8; 64];
+        let options = DefaultOptions::new();
+        let len = options.serialize_into_slice(&mut buf, &value).unwrap();
+
+        let decoded: (&str, &[u8], u32) = options.deserialize(&buf[..len]).unwrap();
+        assert_eq!(decoded, value);
+
+        let err = options
+            .deserialize::<(&str, &[u8], u32)>(&buf[..len - 1])
+            .unwrap_err();
+        let kind: &ErrorKind = &err;
+        match kind {
+            ErrorKind::Io(_) => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_borrowed_round_trip() {
+        let values = [
+            Instruction::Transfer {
+                lamports: 1 << 40,
+                memo: Some("rent"),
+            },
+            Instruction::Transfer {
+                lamports: 0,
+                memo: None,
+            },
+            Instruction::Assign(&[0xff; 40]),
+            Instruction::Close,
+        ];
+        for value in &values {
+            round_trip(DefaultOptions::new(), value);
+            round_trip(
+                DefaultOptions::new()
+                    .with_fixint_encoding()
+                    .with_big_endian(),
+                value,
+            );
+        }
+    }
+
+    #[test]
+    fn test_borrowed_decode_errors() {
+        let options = DefaultOptions::new();
+        match kind(&options.deserialize::<bool>(&[2]).unwrap_err()) {
+            ErrorKind::InvalidBoolEncoding(2) => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+        match kind(&options.deserialize::<char>(&[0xff]).unwrap_err()) {
+            ErrorKind::InvalidCharEncoding => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+        match kind(&options.deserialize::<&str>(&[2, 0xc3, 0x28]).unwrap_err()) {
+            ErrorKind::InvalidUtf8Encoding(_) => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+        match kind(&options.deserialize::<Instruction>(&[3]).unwrap_err()) {
+            ErrorKind::Custom(_) => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+        match kind(
+            &options
+                .reject_trailing_bytes()
+                .deserialize::<u8>(&[1, 2])
+                .unwrap_err(),
+        ) {
+            ErrorKind::Custom(_) => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        // A borrowed slice longer than the collection limit is rejected without reading it.
+        let mut buf = [0u8; 64];
+        let len = options
+            .serialize_into_slice(&mut buf, &Instruction::Assign(&[1; 40]))
+            .unwrap();
+        match kind(
+            &options
+                .with_max_collection_len(32)
+                .deserialize::<Instruction>(&buf[..len])
+                .unwrap_err(),
+        ) {
+            ErrorKind::CollectionLengthLimit { len: 40, max: 32 } => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+        match kind(
+            &options
+                .with_max_depth(1)
+                .deserialize::<Option<Option<u8>>>(&[1, 1, 0])
+                .unwrap_err(),
+        ) {
+            ErrorKind::DepthLimitExceeded => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        match kind(
+            &options
+                .serialize_into_slice(&mut buf[..8], &Instruction::Assign(&[1; 40]))
+                .unwrap_err(),
+        ) {
+            ErrorKind::BufferTooSmall {
+                needed: 42,
+                available: 8,
+            } => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
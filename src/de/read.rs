@@ -1,7 +1,8 @@
 use crate::error::Result;
+use crate::io;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
 use serde;
-use core2::io;
-use alloc::{vec, vec::Vec, boxed::Box};
 
 /// An optional Read trait for advanced Bincode usage.
 ///
@@ -19,12 +20,19 @@ pub trait BincodeRead<'storage>: io::Read {
         V: serde::de::Visitor<'storage>;
 
     /// Transfer ownership of the next `length` bytes to the caller.
+    #[cfg(feature = "alloc")]
     fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>>;
 
     /// Pass a slice of the next `length` bytes on to the serde reader.
     fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'storage>;
+
+    /// Returns true if the forward_read_* methods copy the next `length` bytes into a
+    /// buffer of their own instead of borrowing them from the source.
+    fn forward_reads_allocate(&self) -> bool {
+        false
+    }
 }
 
 /// A BincodeRead implementation for byte slices
@@ -33,11 +41,30 @@ pub struct SliceReader<'storage> {
 }
 
 /// A BincodeRead implementation for `io::Read`ers
+#[cfg(feature = "alloc")]
 pub struct IoReader<R> {
     reader: R,
     temp_buffer: Vec<u8>,
 }
 
+/// A BincodeRead implementation for `io::BufRead`ers
+///
+/// Strings and byte arrays that are already in the reader's buffer are passed on straight from
+/// it, and are only copied when they span a refill.
+#[cfg(feature = "std")]
+pub struct BufReadReader<R> {
+    reader: R,
+    temp_buffer: Vec<u8>,
+}
+
+/// A BincodeRead implementation for the bytes of a value that have arrived so far, which
+/// records how many more bytes a read needed when they ran out.
+#[cfg(feature = "alloc")]
+pub(crate) struct PartialReader<'storage> {
+    slice: &'storage [u8],
+    missing: usize,
+}
+
 impl<'storage> SliceReader<'storage> {
     /// Constructs a slice reader
     pub(crate) fn new(bytes: &'storage [u8]) -> SliceReader<'storage> {
@@ -54,11 +81,48 @@ impl<'storage> SliceReader<'storage> {
         Ok(read_slice)
     }
 
+    /// Returns the bytes that have not been read yet.
+    pub fn remaining(&self) -> &'storage [u8] {
+        self.slice
+    }
+
     pub(crate) fn is_finished(&self) -> bool {
         self.slice.is_empty()
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<'storage> PartialReader<'storage> {
+    pub(crate) fn new(bytes: &'storage [u8]) -> PartialReader<'storage> {
+        PartialReader {
+            slice: bytes,
+            missing: 0,
+        }
+    }
+
+    fn get_byte_slice(&mut self, length: usize) -> io::Result<&'storage [u8]> {
+        if length > self.slice.len() {
+            self.missing = length - self.slice.len();
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let (read_slice, remaining) = self.slice.split_at(length);
+        self.slice = remaining;
+        Ok(read_slice)
+    }
+
+    /// Returns how many more bytes the read that ran out of them needed, which is a lower bound
+    /// on the number of bytes still missing from the value, or 0 if no read has run out.
+    pub(crate) fn missing(&self) -> usize {
+        self.missing
+    }
+
+    /// Returns the bytes that have not been read yet.
+    pub(crate) fn remaining(&self) -> &'storage [u8] {
+        self.slice
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl<R> IoReader<R> {
     /// Constructs an IoReadReader
     pub(crate) fn new(r: R) -> IoReader<R> {
@@ -67,6 +131,26 @@ impl<R> IoReader<R> {
             temp_buffer: vec![],
         }
     }
+
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> BufReadReader<R> {
+    /// Constructs a BufReadReader
+    pub fn new(r: R) -> BufReadReader<R> {
+        BufReadReader {
+            reader: r,
+            temp_buffer: vec![],
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
 }
 
 impl<'storage> io::Read for SliceReader<'storage> {
@@ -88,6 +172,19 @@ impl<'storage> io::Read for SliceReader<'storage> {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<'storage> io::Read for PartialReader<'storage> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        out.copy_from_slice(self.get_byte_slice(out.len())?);
+        Ok(out.len())
+    }
+
+    fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
+        self.read(out).map(|_| ())
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl<R: io::Read> io::Read for IoReader<R> {
     #[inline(always)]
     fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
@@ -99,13 +196,22 @@ impl<R: io::Read> io::Read for IoReader<R> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<R: io::BufRead> io::Read for BufReadReader<R> {
+    #[inline(always)]
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(out)
+    }
+    #[inline(always)]
+    fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
+        self.reader.read_exact(out)
+    }
+}
+
 impl<'storage> SliceReader<'storage> {
     #[inline(always)]
-    fn unexpected_eof() -> Box<crate::ErrorKind> {
-        Box::new(crate::ErrorKind::Io(io::Error::new(
-            io::ErrorKind::UnexpectedEof,
-            "",
-        )))
+    fn unexpected_eof() -> crate::Error {
+        crate::ErrorKind::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "")).into()
     }
 }
 
@@ -124,6 +230,7 @@ impl<'storage> BincodeRead<'storage> for SliceReader<'storage> {
     }
 
     #[inline(always)]
+    #[cfg(feature = "alloc")]
     fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
         self.get_byte_slice(length).map(|x| x.to_vec())
     }
@@ -137,6 +244,32 @@ impl<'storage> BincodeRead<'storage> for SliceReader<'storage> {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<'storage> BincodeRead<'storage> for PartialReader<'storage> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        let string = match core::str::from_utf8(self.get_byte_slice(length)?) {
+            Ok(s) => s,
+            Err(e) => return Err(crate::ErrorKind::InvalidUtf8Encoding(e).into()),
+        };
+        visitor.visit_borrowed_str(string)
+    }
+
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        Ok(self.get_byte_slice(length)?.to_vec())
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        visitor.visit_borrowed_bytes(self.get_byte_slice(length)?)
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl<R> IoReader<R>
 where
     R: io::Read,
@@ -150,6 +283,7 @@ where
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<'a, R> BincodeRead<'a> for IoReader<R>
 where
     R: io::Read,
@@ -180,9 +314,87 @@ where
         self.fill_buffer(length)?;
         visitor.visit_bytes(&self.temp_buffer[..])
     }
+
+    fn forward_reads_allocate(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> BufReadReader<R>
+where
+    R: io::BufRead,
+{
+    /// Passes the next `length` bytes to `f`, straight from the reader's buffer if they are
+    /// all in it, and copied into `temp_buffer` otherwise.
+    fn with_bytes<T, F>(&mut self, length: usize, f: F) -> Result<T>
+    where
+        F: FnOnce(&[u8]) -> Result<T>,
+    {
+        let buffered = loop {
+            match self.reader.fill_buf() {
+                Ok(buffered) => break buffered,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        };
+        if buffered.len() >= length {
+            let result = f(&buffered[..length]);
+            self.reader.consume(length);
+            return result;
+        }
+
+        self.temp_buffer.clear();
+        read_arriving(&mut self.reader, length, &mut self.temp_buffer)?;
+        f(&self.temp_buffer)
+    }
+}
+
+/// Appends the next `length` bytes of `reader` to `buffer`, which only grows as they arrive, so
+/// that a length read from untrusted input is never allocated before its bytes are there.
+#[cfg(feature = "std")]
+fn read_arriving<R: io::Read>(reader: R, length: usize, buffer: &mut Vec<u8>) -> Result<()> {
+    let read = io::Read::read_to_end(&mut reader.take(length as u64), buffer)?;
+    if read < length {
+        return Err(crate::ErrorKind::Io(io::ErrorKind::UnexpectedEof.into()).into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+impl<'a, R> BincodeRead<'a> for BufReadReader<R>
+where
+    R: io::BufRead,
+{
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'a>,
+    {
+        self.with_bytes(length, |bytes| match core::str::from_utf8(bytes) {
+            Ok(s) => visitor.visit_str(s),
+            Err(e) => Err(crate::ErrorKind::InvalidUtf8Encoding(e).into()),
+        })
+    }
+
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        read_arriving(&mut self.reader, length, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'a>,
+    {
+        self.with_bytes(length, |bytes| visitor.visit_bytes(bytes))
+    }
+
+    fn forward_reads_allocate(&self) -> bool {
+        true
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod test {
     use super::IoReader;
     use alloc::vec;
@@ -201,4 +413,67 @@ mod test {
         reader.fill_buffer(5).unwrap();
         assert_eq!(5, reader.temp_buffer.len());
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_buf_read_borrows_from_buffer() {
+        use super::{BincodeRead, BufReadReader};
+        use std::io::BufReader;
+
+        /// Returns where the bytes it was passed were.
+        struct Location;
+
+        impl<'de> serde::de::Visitor<'de> for Location {
+            type Value = *const u8;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("bytes")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<*const u8, E> {
+                Ok(v.as_ptr())
+            }
+        }
+
+        let buffer = vec![7u8; 16];
+        let mut reader = BufReadReader::new(BufReader::with_capacity(8, buffer.as_slice()));
+
+        // the first 6 bytes are in the reader's buffer
+        let first = reader.forward_read_bytes(6, Location).unwrap();
+        assert!(reader.temp_buffer.is_empty());
+
+        // the next 6 span a refill, so they are copied
+        let second = reader.forward_read_bytes(6, Location).unwrap();
+        assert_eq!(second, reader.temp_buffer.as_ptr());
+
+        // and the last 4 are in the refilled buffer
+        let third = reader.forward_read_bytes(4, Location).unwrap();
+        assert_eq!(third, first.wrapping_add(4));
+        assert!(reader.forward_read_bytes(1, Location).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_buf_read_does_not_trust_lengths() {
+        use super::{BincodeRead, BufReadReader};
+        use std::io::BufReader;
+
+        // a length that can not be allocated fails when the bytes run out instead
+        let buffer = vec![7u8; 16];
+        let mut reader = BufReadReader::new(BufReader::with_capacity(8, buffer.as_slice()));
+        match *reader.get_byte_buffer(usize::MAX >> 1).unwrap_err() {
+            crate::ErrorKind::Io(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+
+        let mut reader = BufReadReader::new(BufReader::with_capacity(8, buffer.as_slice()));
+        match *reader
+            .forward_read_bytes(usize::MAX >> 1, serde::de::IgnoredAny)
+            .unwrap_err()
+        {
+            crate::ErrorKind::Io(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+        assert!(reader.temp_buffer.capacity() < 1024);
+    }
 }
@@ -0,0 +1,122 @@
+use core::marker::PhantomData;
+
+use super::read::IoReader;
+use super::Deserializer;
+use crate::config::Options;
+use crate::io::{self, Read};
+use crate::Result;
+
+/// A `Read`er that puts a byte it peeked at back in front of the rest.
+struct Peeked<R> {
+    byte: Option<u8>,
+    reader: R,
+}
+
+impl<R: Read> Peeked<R> {
+    /// Returns true if the reader is at its end, reading ahead one byte if it is not.
+    fn at_end(&mut self) -> io::Result<bool> {
+        if self.byte.is_some() {
+            return Ok(false);
+        }
+        let mut byte = [0u8];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(true),
+                Ok(_) => {
+                    self.byte = Some(byte[0]);
+                    return Ok(false);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for Peeked<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        match self.byte.take() {
+            Some(byte) if !out.is_empty() => {
+                out[0] = byte;
+                Ok(1)
+            }
+            byte => {
+                self.byte = byte;
+                self.reader.read(out)
+            }
+        }
+    }
+
+    fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
+        match self.byte.take() {
+            Some(byte) if !out.is_empty() => {
+                out[0] = byte;
+                self.reader.read_exact(&mut out[1..])
+            }
+            byte => {
+                self.byte = byte;
+                self.reader.read_exact(out)
+            }
+        }
+    }
+}
+
+/// An iterator over the objects stored back to back in a `Read`er, as returned by
+/// `Options::deserialize_stream`.
+///
+/// The iterator ends when the reader ends between two objects. A reader that ends in the middle
+/// of an object yields an `ErrorKind::Io` error with `io::ErrorKind::UnexpectedEof` instead,
+/// and the iterator ends after any error, since the reader is left in an unknown position.
+///
+/// Every object is deserialized with a fresh copy of the options, so a size limit applies to
+/// each object on its own.
+pub struct StreamDeserializer<R, T, O> {
+    reader: Option<IoReader<Peeked<R>>>,
+    options: O,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T, O> StreamDeserializer<R, T, O> {
+    pub(crate) fn new(reader: R, options: O) -> StreamDeserializer<R, T, O> {
+        StreamDeserializer {
+            reader: Some(IoReader::new(Peeked { byte: None, reader })),
+            options,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<R, T, O> Iterator for StreamDeserializer<R, T, O>
+where
+    R: Read,
+    T: serde::de::DeserializeOwned,
+    O: Options + Clone,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        let mut reader = self.reader.take()?;
+        match reader.get_mut().at_end() {
+            Ok(true) => return None,
+            Ok(false) => {}
+            Err(e) => return Some(Err(e.into())),
+        }
+
+        let mut deserializer = Deserializer::with_bincode_read(reader, self.options.clone());
+        match serde::Deserialize::deserialize(&mut deserializer) {
+            Ok(value) => {
+                self.reader = Some(deserializer.reader);
+                Some(Ok(value))
+            }
+            Err(err) => Some(Err(deserializer.with_error_context(err))),
+        }
+    }
+}
+
+impl<R, T, O> core::iter::FusedIterator for StreamDeserializer<R, T, O>
+where
+    R: Read,
+    T: serde::de::DeserializeOwned,
+    O: Options + Clone,
+{
+}
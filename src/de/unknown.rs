@@ -0,0 +1,225 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{self, IntoDeserializer};
+use serde::ser::{self, SerializeTupleVariant};
+
+use crate::{Error, Result};
+
+/// The index and the payload of an enum variant that the reader does not know.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownVariant {
+    /// The index of the variant.
+    pub index: u32,
+    /// The payload of the variant, as it was serialized.
+    pub payload: Vec<u8>,
+}
+
+/// An enum `E`, or a variant of it that was added after `E` was compiled.
+///
+/// With `Options::with_variant_evolution`, reading an `OrUnknown<E>` keeps a variant whose index
+/// is past the variants of `E` as an [`UnknownVariant`] instead of failing or reading it as the
+/// `#[serde(other)]` variant of `E`. Writing it back out with the same options reproduces the
+/// bytes it was read from, so a value can be passed on by a program that does not know all of
+/// its variants. Without variant evolution the payload of a variant has no length, so reading an
+/// unknown variant fails.
+///
+/// The payload is bounded by the size limit, but is not charged to the allocation limit.
+///
+/// ```
+/// # use bincode::{de::{OrUnknown, UnknownVariant}, DefaultOptions, Options};
+/// # use serde_derive::{Deserialize, Serialize};
+/// #[derive(Serialize)]
+/// enum MessageV2 {
+///     Ping,
+///     Transfer(u8),
+/// }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// enum MessageV1 {
+///     Ping,
+/// }
+///
+/// let options = DefaultOptions::new().with_variant_evolution();
+/// let bytes = options.serialize(&MessageV2::Transfer(7)).unwrap();
+/// let read: OrUnknown<MessageV1> = options.deserialize(&bytes).unwrap();
+/// let unknown = UnknownVariant {
+///     index: 1,
+///     payload: vec![7],
+/// };
+/// assert_eq!(read, OrUnknown::Unknown(unknown));
+/// assert_eq!(options.serialize(&read).unwrap(), bytes);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OrUnknown<E> {
+    /// A variant of `E`.
+    Known(E),
+    /// A variant that `E` does not have.
+    Unknown(UnknownVariant),
+}
+
+impl<E: ser::Serialize> ser::Serialize for OrUnknown<E> {
+    fn serialize<S: ser::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        match *self {
+            OrUnknown::Known(ref value) => value.serialize(serializer),
+            OrUnknown::Unknown(ref unknown) => {
+                // every byte is a `u8` field, which every int encoding writes as it is
+                let mut variant = serializer.serialize_tuple_variant(
+                    "OrUnknown",
+                    unknown.index,
+                    "Unknown",
+                    unknown.payload.len(),
+                )?;
+                for byte in &unknown.payload {
+                    variant.serialize_field(byte)?;
+                }
+                variant.end()
+            }
+        }
+    }
+}
+
+impl<'de, E: de::Deserialize<'de>> de::Deserialize<'de> for OrUnknown<E> {
+    fn deserialize<D: de::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        // `E` only tells its name and variants to the deserializer it is read from
+        let mut probe = Probe(None);
+        let _ = E::deserialize(&mut probe);
+        let (name, variants) = probe
+            .0
+            .ok_or_else(|| de::Error::custom("`OrUnknown` must wrap an enum"))?;
+        deserializer.deserialize_enum(
+            name,
+            variants,
+            OrUnknownVisitor {
+                variants,
+                marker: PhantomData,
+            },
+        )
+    }
+}
+
+/// Records the name and the variants of the enum that is read from it, and reads nothing.
+struct Probe(Option<(&'static str, &'static [&'static str])>);
+
+impl<'de, 'a> de::Deserializer<'de> for &'a mut Probe {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(de::Error::custom("`OrUnknown` must wrap an enum"))
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value> {
+        self.0 = Some((name, variants));
+        Err(de::Error::custom("probed"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+struct OrUnknownVisitor<E> {
+    variants: &'static [&'static str],
+    marker: PhantomData<fn() -> E>,
+}
+
+impl<'de, E: de::Deserialize<'de>> de::Visitor<'de> for OrUnknownVisitor<E> {
+    type Value = OrUnknown<E>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an enum variant")
+    }
+
+    fn visit_enum<A: de::EnumAccess<'de>>(
+        self,
+        data: A,
+    ) -> core::result::Result<OrUnknown<E>, A::Error> {
+        let (index, variant): (u32, _) = data.variant()?;
+        if (index as usize) < self.variants.len() {
+            return E::deserialize(Replay { index, variant }).map(OrUnknown::Known);
+        }
+        // the payload is read up to the length in front of the variant
+        let payload = de::VariantAccess::tuple_variant(variant, usize::MAX, PayloadVisitor)?;
+        Ok(OrUnknown::Unknown(UnknownVariant { index, payload }))
+    }
+}
+
+/// Hands a variant whose index has already been read to the enum it belongs to.
+struct Replay<A> {
+    index: u32,
+    variant: A,
+}
+
+impl<'de, A: de::VariantAccess<'de>> de::Deserializer<'de> for Replay<A> {
+    type Error = A::Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> core::result::Result<V::Value, A::Error> {
+        Err(de::Error::custom("`OrUnknown` must wrap an enum"))
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> core::result::Result<V::Value, A::Error> {
+        visitor.visit_enum(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+impl<'de, A: de::VariantAccess<'de>> de::EnumAccess<'de> for Replay<A> {
+    type Error = A::Error;
+    type Variant = A;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> core::result::Result<(V::Value, A), A::Error> {
+        let value = seed.deserialize(self.index.into_deserializer())?;
+        Ok((value, self.variant))
+    }
+}
+
+/// Reads the payload of an unknown variant byte by byte, as it grows only as bytes arrive.
+struct PayloadVisitor;
+
+impl<'de> de::Visitor<'de> for PayloadVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("the payload of an unknown variant")
+    }
+
+    fn visit_seq<S: de::SeqAccess<'de>>(
+        self,
+        mut seq: S,
+    ) -> core::result::Result<Vec<u8>, S::Error> {
+        let mut payload = Vec::new();
+        while let Some(byte) = seq.next_element()? {
+            payload.push(byte);
+        }
+        Ok(payload)
+    }
+}
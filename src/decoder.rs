@@ -0,0 +1,124 @@
+//! Incremental deserialization of a value whose bytes arrive in fragments.
+//!
+//! Serde deserializers pull their input and can not be suspended, so a [`Decoder`] does not
+//! resume where it stopped: it buffers the bytes fed to it, and every attempt to deserialize the
+//! value parses the whole buffer again from its first byte. When an attempt runs out of bytes,
+//! the read it stopped at tells how many more it needed, which is a lower bound on what is still
+//! missing, and no new attempt is made until at least that many more bytes have been fed.
+//!
+//! A value fed in `k` fragments that each complete such a read is therefore parsed `k` times,
+//! which is quadratic in the number of fragments. Values made of many small elements that
+//! arrive in small fragments are better sent as frames, for example with a length prefix, and
+//! only decoded once the whole frame is there.
+
+use alloc::vec::Vec;
+use core::task::Poll;
+
+use crate::config::Options;
+use crate::de::read::PartialReader;
+use crate::Result;
+
+/// Deserializes a value of type `T` from bytes that are fed to it as they arrive.
+///
+/// ```
+/// # use bincode::{decoder::Decoder, DefaultOptions, Options};
+/// # use std::task::Poll;
+/// let bytes = DefaultOptions::new().serialize(&("hello", 42u32)).unwrap();
+/// let mut decoder = Decoder::<(String, u32), _>::new(DefaultOptions::new());
+/// assert!(decoder.feed(&bytes[..3]).is_pending());
+/// assert_eq!(decoder.needed(), 3);
+/// match decoder.feed(&bytes[3..]) {
+///     Poll::Ready(value) => assert_eq!(value.unwrap(), ("hello".to_string(), 42)),
+///     Poll::Pending => unreachable!(),
+/// }
+/// ```
+pub struct Decoder<T, O> {
+    options: O,
+    /// The bytes fed so far, and once the value is decoded, the bytes fed past its end.
+    buffer: Vec<u8>,
+    /// The number of buffered bytes the next attempt needs at least.
+    wanted: usize,
+    /// The result, if the value was complete before anything was fed.
+    ready: Option<Result<T>>,
+    finished: bool,
+}
+
+impl<T, O> Decoder<T, O>
+where
+    T: serde::de::DeserializeOwned,
+    O: Options + Clone,
+{
+    /// Starts decoding a value using `options`.
+    pub fn new(options: O) -> Decoder<T, O> {
+        let mut decoder = Decoder {
+            options,
+            buffer: Vec::new(),
+            wanted: 0,
+            ready: None,
+            finished: false,
+        };
+        // find out how many bytes the value needs first, unless it has none
+        if let Poll::Ready(result) = decoder.attempt() {
+            decoder.ready = Some(result);
+        }
+        decoder
+    }
+
+    /// Feeds the next `bytes` of the value.
+    ///
+    /// Returns the value, or the error that stopped it from being deserialized, once all of
+    /// its bytes have been fed, and `Poll::Pending` until then. Once `needed` bytes have been
+    /// fed, the value is deserialized again from the first buffered byte. Bytes fed past the
+    /// end of the value are kept in `remainder`. Feeding a decoder that has finished returns
+    /// an error.
+    pub fn feed(&mut self, bytes: &[u8]) -> Poll<Result<T>> {
+        if let Some(result) = self.ready.take() {
+            self.buffer.extend_from_slice(bytes);
+            return Poll::Ready(result);
+        }
+        if self.finished {
+            return Poll::Ready(Err(custom_error!("the decoder has already finished").into()));
+        }
+        self.buffer.extend_from_slice(bytes);
+        if self.buffer.len() < self.wanted {
+            return Poll::Pending;
+        }
+        self.attempt()
+    }
+
+    /// Deserializes the value from the start of the buffered bytes.
+    fn attempt(&mut self) -> Poll<Result<T>> {
+        let reader = PartialReader::new(&self.buffer);
+        let mut deserializer =
+            crate::de::Deserializer::with_bincode_read(reader, self.options.clone());
+        let result = match serde::Deserialize::deserialize(&mut deserializer) {
+            Err(_) if deserializer.reader.missing() > 0 => {
+                self.wanted = self.buffer.len() + deserializer.reader.missing();
+                return Poll::Pending;
+            }
+            result => result.map_err(|err| deserializer.with_error_context(err)),
+        };
+        let read = self.buffer.len() - deserializer.reader.remaining().len();
+        self.buffer.drain(..read);
+        self.wanted = 0;
+        self.finished = true;
+        Poll::Ready(result)
+    }
+}
+
+impl<T, O> Decoder<T, O> {
+    /// Returns a lower bound on the number of bytes that still have to be fed before the value
+    /// is complete.
+    pub fn needed(&self) -> usize {
+        self.wanted.saturating_sub(self.buffer.len())
+    }
+
+    /// Returns the bytes that were fed past the end of the value once it has been decoded.
+    pub fn remainder(&self) -> &[u8] {
+        if self.finished {
+            &self.buffer
+        } else {
+            &[]
+        }
+    }
+}
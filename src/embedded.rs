@@ -0,0 +1,107 @@
+//! Adapters for the `embedded_io::Read` and `embedded_io::Write` traits, which the embedded
+//! ecosystem uses in place of `std::io`.
+//!
+//! `Options::serialize_into_embedded` and `Options::deserialize_from_embedded` use these
+//! directly; [`EmbeddedIoReader`] can also be passed to `Deserializer::with_bincode_read`.
+
+use crate::de::read::{BincodeRead, IoReader};
+use crate::error::Result;
+use crate::io;
+use alloc::vec::Vec;
+
+/// Wraps an `embedded_io` reader or writer so that it implements bincode's `Read` and
+/// `Write` traits.
+pub struct EmbeddedIo<T>(pub T);
+
+// `io::Error::other` is not available from core2 or the crate's own io module
+#[allow(clippy::io_other_error)]
+fn io_error<E: embedded_io::Error>(err: E) -> io::Error {
+    match err.kind() {
+        embedded_io::ErrorKind::Interrupted => {
+            io::Error::new(io::ErrorKind::Interrupted, "operation interrupted")
+        }
+        embedded_io::ErrorKind::WriteZero => {
+            io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer")
+        }
+        _ => io::Error::new(io::ErrorKind::Other, "embedded-io error"),
+    }
+}
+
+impl<R: embedded_io::Read> io::Read for EmbeddedIo<R> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(io_error)
+    }
+
+    #[inline(always)]
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.0.read_exact(buf).map_err(|err| match err {
+            embedded_io::ReadExactError::UnexpectedEof => {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer")
+            }
+            embedded_io::ReadExactError::Other(err) => io_error(err),
+        })
+    }
+}
+
+impl<W: embedded_io::Write> io::Write for EmbeddedIo<W> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf).map_err(io_error)
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush().map_err(io_error)
+    }
+}
+
+/// A BincodeRead implementation for `embedded_io::Read`ers
+pub struct EmbeddedIoReader<R> {
+    reader: IoReader<EmbeddedIo<R>>,
+}
+
+impl<R: embedded_io::Read> EmbeddedIoReader<R> {
+    /// Constructs an EmbeddedIoReader
+    pub fn new(reader: R) -> EmbeddedIoReader<R> {
+        EmbeddedIoReader {
+            reader: IoReader::new(EmbeddedIo(reader)),
+        }
+    }
+}
+
+impl<R: embedded_io::Read> io::Read for EmbeddedIoReader<R> {
+    #[inline(always)]
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(out)
+    }
+
+    #[inline(always)]
+    fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
+        self.reader.read_exact(out)
+    }
+}
+
+impl<'a, R: embedded_io::Read> BincodeRead<'a> for EmbeddedIoReader<R> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'a>,
+    {
+        self.reader.forward_read_str(length, visitor)
+    }
+
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        self.reader.get_byte_buffer(length)
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'a>,
+    {
+        self.reader.forward_read_bytes(length, visitor)
+    }
+
+    fn forward_reads_allocate(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,206 @@
+//! A header that records how a value was serialized, so that reading it back with different
+//! options is an error instead of garbage.
+//!
+//! An enveloped value starts with a 12 byte header: a 4 byte magic number, a fingerprint of the
+//! options it was serialized with, and the schema version of the [`Envelope`], both as
+//! little-endian u32s regardless of the options. The fingerprint covers everything about the
+//! options that changes the format: the endianness, the int encoding, the length and enum tag
+//! encodings, whether type tags are written, whether structs and enum variants are prefixed by
+//! their length, and whether trailing bytes are allowed. It does not cover limits or error
+//! context, which only change what is accepted.
+//!
+//! `Options::serialize_enveloped` writes the header and `Options::deserialize_enveloped` checks
+//! it before deserializing anything.
+
+use serde::ser::{SerializeSeq, SerializeStruct};
+
+use crate::byteorder::{ByteOrder, LittleEndian};
+use crate::config::{Options, TrailingBytes};
+use crate::de::read::SliceReader;
+use crate::frame::crc32c;
+use crate::io::Write;
+use crate::{ErrorKind, Result};
+
+/// The length of the header in front of an enveloped value.
+const HEADER_LEN: usize = 12;
+
+/// The magic number and schema version written in front of an enveloped value.
+///
+/// ```
+/// # use bincode::{envelope::Envelope, DefaultOptions, Options};
+/// let mut bytes = Vec::new();
+/// DefaultOptions::new()
+///     .serialize_enveloped(Envelope::new(3), &mut bytes, &42u64)
+///     .unwrap();
+///
+/// // the same options and version read the value back
+/// let value: u64 = DefaultOptions::new()
+///     .deserialize_enveloped(Envelope::new(3), &bytes)
+///     .unwrap();
+/// assert_eq!(value, 42);
+///
+/// // but other options are caught before anything is deserialized
+/// assert!(DefaultOptions::new()
+///     .with_fixint_encoding()
+///     .deserialize_enveloped::<u64>(Envelope::new(3), &bytes)
+///     .is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    magic: [u8; 4],
+    version: u32,
+}
+
+impl Envelope {
+    /// The magic number used unless another one is set with `with_magic`.
+    pub const DEFAULT_MAGIC: [u8; 4] = *b"BNCD";
+
+    /// Creates an envelope with schema version `version` and the default magic number.
+    pub fn new(version: u32) -> Envelope {
+        Envelope {
+            magic: Envelope::DEFAULT_MAGIC,
+            version,
+        }
+    }
+
+    /// Sets the magic number, for example to tell the files of one application apart from
+    /// another's.
+    pub fn with_magic(mut self, magic: [u8; 4]) -> Envelope {
+        self.magic = magic;
+        self
+    }
+
+    fn header<O: Options>(self, options: &mut O) -> Result<[u8; HEADER_LEN]> {
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(&self.magic);
+        LittleEndian::write_u32(&mut header[4..8], fingerprint(options)?);
+        LittleEndian::write_u32(&mut header[8..], self.version);
+        Ok(header)
+    }
+}
+
+/// A value that exercises every option that changes the format.
+struct Probe;
+
+/// A sequence long enough that every length encoding writes its length differently, and short
+/// enough to fit in all of them.
+struct ProbeLen;
+
+impl serde::Serialize for ProbeLen {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(251))?;
+        for _ in 0..251 {
+            seq.serialize_element(&())?;
+        }
+        seq.end()
+    }
+}
+
+/// An enum variant with an index that every enum tag writes differently, and that fits in all
+/// of them.
+struct ProbeVariant;
+
+impl serde::Serialize for ProbeVariant {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_variant("Probe", 251, "Variant", &())
+    }
+}
+
+impl serde::Serialize for Probe {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        let mut probe = serializer.serialize_struct("Probe", 5)?;
+        probe.serialize_field("endian", &0x0102u16)?;
+        probe.serialize_field("int", &1u64)?;
+        probe.serialize_field("signed", &-1i32)?;
+        probe.serialize_field("len", &ProbeLen)?;
+        probe.serialize_field("variant", &ProbeVariant)?;
+        probe.end()
+    }
+}
+
+/// A `Write`r that only computes the checksum of what is written to it.
+struct Checksum(u32);
+
+impl Write for Checksum {
+    fn write(&mut self, buf: &[u8]) -> crate::io::Result<usize> {
+        self.0 = crc32c(self.0, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> crate::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the fingerprint of the format `options` serialize with.
+fn fingerprint<O: Options>(options: &mut O) -> Result<u32> {
+    let mut checksum = Checksum(0);
+    // the limits do not change the format, and must not stop the probe from being serialized
+    let options = options
+        .with_no_limit()
+        .with_no_max_depth()
+        .with_no_alloc_limit()
+        .with_no_max_collection_len();
+    crate::internal::serialize_into(&mut checksum, &Probe, options)?;
+    let trailing = O::Trailing::check_end(&SliceReader::new(&[0])).is_ok();
+    Ok(crc32c(checksum.0, &[trailing as u8]))
+}
+
+pub(crate) fn serialize_enveloped<W, T, O>(
+    envelope: Envelope,
+    mut writer: W,
+    value: &T,
+    mut options: O,
+) -> Result<()>
+where
+    W: Write,
+    T: ?Sized + serde::Serialize,
+    O: Options,
+{
+    let header = envelope.header(&mut options)?;
+    writer.write_all(&header)?;
+    crate::internal::serialize_into(writer, value, options)
+}
+
+pub(crate) fn deserialize_enveloped<'a, T, O>(
+    envelope: Envelope,
+    bytes: &'a [u8],
+    mut options: O,
+) -> Result<T>
+where
+    T: serde::Deserialize<'a>,
+    O: Options,
+{
+    if bytes.len() < HEADER_LEN {
+        let eof = crate::io::Error::new(crate::io::ErrorKind::UnexpectedEof, "");
+        return Err(ErrorKind::Io(eof).into());
+    }
+    if bytes[..4] != envelope.magic {
+        return Err(ErrorKind::InvalidMagic.into());
+    }
+    let (header, payload) = bytes.split_at(HEADER_LEN);
+
+    let expected = fingerprint(&mut options)?;
+    let found = LittleEndian::read_u32(&header[4..8]);
+    if found != expected {
+        return Err(ErrorKind::FingerprintMismatch { expected, found }.into());
+    }
+    let found = LittleEndian::read_u32(&header[8..]);
+    if found != envelope.version {
+        return Err(ErrorKind::SchemaVersionMismatch {
+            expected: envelope.version,
+            found,
+        }
+        .into());
+    }
+    crate::internal::deserialize(payload, options)
+}
@@ -1,17 +1,26 @@
 use core::error::Error as StdError;
-use core2::io;
+use crate::io;
 use core::str::Utf8Error;
 use core::fmt;
+#[cfg(feature = "alloc")]
 use alloc::{boxed::Box, string::{String, ToString}};
 
 /// The result of a serialization or deserialization operation.
 pub type Result<T> = core::result::Result<T, Error>;
 
 /// An error that can be produced during (de)serializing.
+#[cfg(feature = "alloc")]
 pub type Error = Box<ErrorKind>;
 
+/// An error that can be produced during (de)serializing.
+///
+/// Without the `alloc` feature errors are not boxed, and `ErrorKind` is `Copy`.
+#[cfg(not(feature = "alloc"))]
+pub type Error = ErrorKind;
+
 /// The kind of error that can be produced during a serialization or deserialization.
 #[derive(Debug)]
+#[cfg_attr(not(feature = "alloc"), derive(Clone, Copy))]
 pub enum ErrorKind {
     /// If the error stems from the reader/writer that is being used
     /// during (de)serialization, that error will be stored and returned here.
@@ -27,15 +36,104 @@ pub enum ErrorKind {
     /// not in the expected ranges
     InvalidTagEncoding(usize),
     /// Serde has a deserialize_any method that lets the format hint to the
-    /// object which route to take in deserializing.
+    /// object which route to take in deserializing. Bincode only supports it
+    /// when `Options::with_self_describing` is set.
     DeserializeAnyNotSupported,
     /// If (de)serializing a message takes more than the provided size limit, this
     /// error is returned.
     SizeLimit,
-    /// Bincode can not encode sequences of unknown length (like iterators).
+    /// Bincode can not encode sequences of unknown length (like iterators) unless
+    /// `Options::allow_unknown_lengths` is set.
     SequenceMustHaveLength,
+    /// Returned if the index of an enum variant does not fit in the tag width set by
+    /// `Options::with_enum_tag`.
+    EnumTagOverflow {
+        /// The index of the variant being serialized.
+        index: u32,
+        /// The largest index that fits in the tag.
+        max: u32,
+    },
+    /// Returned if a length does not fit in the length encoding set by
+    /// `Options::with_len_encoding`, either when it is serialized or when it is read.
+    LengthOverflow {
+        /// The length being serialized or read.
+        len: u64,
+        /// The largest length the encoding holds.
+        max: u64,
+    },
+    /// Returned if a length prefix is not in the canonical form of its encoding, such as a
+    /// compact-u16 length that ends in a superfluous zero byte.
+    InvalidLengthEncoding,
+    /// Returned if deserializing would allocate more than the limit set by
+    /// `Options::with_alloc_limit`.
+    AllocationLimit {
+        /// The number of bytes the deserializer tried to allocate.
+        requested: u64,
+        /// The number of bytes left in the allocation limit.
+        remaining: u64,
+    },
+    /// Returned if a value is nested deeper than the limit set by `Options::with_max_depth`.
+    DepthLimitExceeded,
+    /// Returned if a length prefix is longer than the limit set by
+    /// `Options::with_max_collection_len`.
+    CollectionLengthLimit {
+        /// The length read from the input.
+        len: u64,
+        /// The maximum length allowed.
+        max: u64,
+    },
+    /// Returned by `Options::serialize_into_slice` if the value does not fit in the slice.
+    BufferTooSmall {
+        /// The number of bytes the serialized value takes.
+        needed: u64,
+        /// The length of the slice.
+        available: u64,
+    },
+    /// Returned by `Options::deserialize_framed` if the checksum of a frame does not match its
+    /// payload.
+    ChecksumMismatch {
+        /// The checksum stored after the payload.
+        expected: u32,
+        /// The checksum of the payload that was read.
+        actual: u32,
+    },
+    /// Returned by `Options::deserialize_enveloped` if the input does not start with the magic
+    /// number of the envelope.
+    InvalidMagic,
+    /// Returned by `Options::deserialize_enveloped` if the value was serialized with options
+    /// that have a different format than the ones it is deserialized with.
+    FingerprintMismatch {
+        /// The fingerprint of the options used to deserialize.
+        expected: u32,
+        /// The fingerprint in the envelope.
+        found: u32,
+    },
+    /// Returned by `Options::deserialize_enveloped` if the value has a different schema version
+    /// than the envelope it is deserialized with.
+    SchemaVersionMismatch {
+        /// The schema version of the envelope used to deserialize.
+        expected: u32,
+        /// The schema version in the envelope.
+        found: u32,
+    },
     /// A custom error message from Serde.
+    #[cfg(feature = "alloc")]
     Custom(String),
+    /// A custom error message. Without the `alloc` feature the message of an error from Serde
+    /// can not be kept, so it is replaced by a fixed one.
+    #[cfg(not(feature = "alloc"))]
+    Custom(&'static str),
+    /// A deserialization error along with where in the input it occurred. This is only
+    /// returned when `Options::with_error_context` is set.
+    #[cfg(feature = "alloc")]
+    Context {
+        /// The byte offset of the read that failed, or of the last read before the error.
+        offset: u64,
+        /// The path to the value that failed, such as `Block.transactions[12].signatures[0]`.
+        path: String,
+        /// The error that occurred.
+        source: Error,
+    },
 }
 
 impl StdError for ErrorKind {
@@ -53,7 +151,23 @@ impl StdError for ErrorKind {
                 "Bincode doesn't support serde::Deserializer::deserialize_any"
             }
             ErrorKind::SizeLimit => "the size limit has been reached",
+            ErrorKind::EnumTagOverflow { .. } => "enum variant index does not fit in the tag",
+            ErrorKind::LengthOverflow { .. } => "length does not fit in the length encoding",
+            ErrorKind::InvalidLengthEncoding => "length prefix is not canonically encoded",
+            ErrorKind::AllocationLimit { .. } => "the allocation limit has been reached",
+            ErrorKind::DepthLimitExceeded => "the depth limit has been exceeded",
+            ErrorKind::CollectionLengthLimit { .. } => "collection length limit exceeded",
+            ErrorKind::BufferTooSmall { .. } => "the buffer is too small",
+            ErrorKind::ChecksumMismatch { .. } => "the checksum of the frame does not match",
+            ErrorKind::InvalidMagic => "the magic number does not match",
+            ErrorKind::FingerprintMismatch { .. } => "the options fingerprint does not match",
+            ErrorKind::SchemaVersionMismatch { .. } => "the schema version does not match",
+            #[cfg(feature = "alloc")]
             ErrorKind::Custom(ref msg) => msg,
+            #[cfg(not(feature = "alloc"))]
+            ErrorKind::Custom(msg) => msg,
+            #[cfg(feature = "alloc")]
+            ErrorKind::Context { ref source, .. } => source.description(),
         }
     }
 
@@ -67,7 +181,20 @@ impl StdError for ErrorKind {
             ErrorKind::SequenceMustHaveLength => None,
             ErrorKind::DeserializeAnyNotSupported => None,
             ErrorKind::SizeLimit => None,
+            ErrorKind::EnumTagOverflow { .. } => None,
+            ErrorKind::LengthOverflow { .. } => None,
+            ErrorKind::InvalidLengthEncoding => None,
+            ErrorKind::AllocationLimit { .. } => None,
+            ErrorKind::DepthLimitExceeded => None,
+            ErrorKind::CollectionLengthLimit { .. } => None,
+            ErrorKind::BufferTooSmall { .. } => None,
+            ErrorKind::ChecksumMismatch { .. } => None,
+            ErrorKind::InvalidMagic => None,
+            ErrorKind::FingerprintMismatch { .. } => None,
+            ErrorKind::SchemaVersionMismatch { .. } => None,
             ErrorKind::Custom(_) => None,
+            #[cfg(feature = "alloc")]
+            ErrorKind::Context { ref source, .. } => Some(&**source),
         }
     }
 }
@@ -98,19 +225,110 @@ impl fmt::Display for ErrorKind {
                 fmt,
                 "Bincode does not support the serde::Deserializer::deserialize_any method"
             ),
+            ErrorKind::EnumTagOverflow { index, max } => write!(
+                fmt,
+                "enum variant index {} does not fit in the tag (0 to {})",
+                index, max
+            ),
+            ErrorKind::LengthOverflow { len, max } => write!(
+                fmt,
+                "the length {} does not fit in the length encoding (0 to {})",
+                len, max
+            ),
+            ErrorKind::InvalidLengthEncoding => {
+                write!(fmt, "the length prefix is not canonically encoded")
+            }
+            ErrorKind::AllocationLimit {
+                requested,
+                remaining,
+            } => write!(
+                fmt,
+                "allocating {} bytes would exceed the allocation limit ({} bytes remaining)",
+                requested, remaining
+            ),
+            ErrorKind::DepthLimitExceeded => write!(fmt, "the depth limit has been exceeded"),
+            ErrorKind::CollectionLengthLimit { len, max } => write!(
+                fmt,
+                "the length {} is longer than the collection length limit of {}",
+                len, max
+            ),
+            ErrorKind::BufferTooSmall { needed, available } => write!(
+                fmt,
+                "the buffer is too small: {} bytes are needed but only {} are available",
+                needed, available
+            ),
+            ErrorKind::ChecksumMismatch { expected, actual } => write!(
+                fmt,
+                "the checksum of the frame does not match: expected {:#010x}, got {:#010x}",
+                expected, actual
+            ),
+            ErrorKind::InvalidMagic => write!(fmt, "the magic number does not match"),
+            ErrorKind::FingerprintMismatch { expected, found } => write!(
+                fmt,
+                "the options fingerprint does not match: expected {:#010x}, found {:#010x}",
+                expected, found
+            ),
+            ErrorKind::SchemaVersionMismatch { expected, found } => write!(
+                fmt,
+                "the schema version does not match: expected {}, found {}",
+                expected, found
+            ),
             ErrorKind::Custom(ref s) => s.fmt(fmt),
+            #[cfg(feature = "alloc")]
+            ErrorKind::Context {
+                offset,
+                ref path,
+                ref source,
+            } => {
+                if path.is_empty() {
+                    write!(fmt, "{} at byte offset {}", source, offset)
+                } else {
+                    write!(fmt, "{} at byte offset {} ({})", source, offset, path)
+                }
+            }
         }
     }
 }
 
+#[cfg(feature = "alloc")]
 impl serde::de::Error for Error {
     fn custom<T: fmt::Display>(desc: T) -> Error {
         ErrorKind::Custom(desc.to_string()).into()
     }
 }
 
+#[cfg(feature = "alloc")]
 impl serde::ser::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         ErrorKind::Custom(msg.to_string()).into()
     }
 }
+
+#[cfg(not(feature = "alloc"))]
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(_desc: T) -> Error {
+        ErrorKind::Custom("the data could not be deserialized")
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        ErrorKind::Custom("the value could not be serialized")
+    }
+}
+
+/// Builds an `ErrorKind::Custom` from a format string, which is only formatted with the
+/// `alloc` feature; otherwise the format string itself is the message.
+macro_rules! custom_error {
+    ($msg:literal $(, $arg:expr)* $(,)?) => {{
+        #[cfg(feature = "alloc")]
+        let error = $crate::ErrorKind::Custom(alloc::format!($msg $(, $arg)*));
+        #[cfg(not(feature = "alloc"))]
+        let error = {
+            $(let _ = $arg;)*
+            $crate::ErrorKind::Custom($msg)
+        };
+        error
+    }};
+}
@@ -0,0 +1,327 @@
+//! Length-delimited frames, for values that are stored one after another on disk or sent over
+//! a byte stream.
+//!
+//! A frame is a header holding the length of the payload, the payload, which is the serialized
+//! value, and, if enabled, a CRC32C checksum of the payload. The length and the checksum are
+//! written with the endianness of the options. `Options::serialize_framed` writes frames and
+//! `Options::deserialize_framed` reads them.
+
+use crate::byteorder::ByteOrder;
+#[cfg(feature = "alloc")]
+use crate::config::SizeLimit;
+use crate::config::{BincodeByteOrder, Options};
+use crate::config::{U16_BYTE, U32_BYTE, U64_BYTE};
+#[cfg(feature = "alloc")]
+use crate::io::Read;
+use crate::io::Write;
+#[cfg(feature = "alloc")]
+use crate::ErrorKind;
+use crate::Result;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// The encoding of the length written in front of every frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameHeader {
+    /// A 2 byte length, for frames of up to 64KiB.
+    U16,
+    /// A 4 byte length, for frames of up to 4GiB. This is the default.
+    U32,
+    /// An 8 byte length.
+    U64,
+    /// A length of 1 to 9 bytes, in the varint format that `Options::with_varint_encoding` uses
+    /// for integers.
+    Varint,
+}
+
+impl FrameHeader {
+    /// The largest header of this kind.
+    pub(crate) const MAX_WIDTH: usize = 9;
+
+    /// Returns the number of bytes the header takes for a frame of `len` bytes.
+    pub(crate) fn width(self, len: u64) -> usize {
+        match self {
+            FrameHeader::U16 => 2,
+            FrameHeader::U32 => 4,
+            FrameHeader::U64 => 8,
+            FrameHeader::Varint => match len {
+                0..=250 => 1,
+                251..=0xFFFF => 3,
+                0x1_0000..=0xFFFF_FFFF => 5,
+                _ => 9,
+            },
+        }
+    }
+
+    /// Returns the number of bytes the header takes, given its first byte.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn width_from_first(self, first: u8) -> Result<usize> {
+        match (self, first) {
+            (FrameHeader::Varint, byte) if byte < U16_BYTE => Ok(1),
+            (FrameHeader::Varint, U16_BYTE) => Ok(3),
+            (FrameHeader::Varint, U32_BYTE) => Ok(5),
+            (FrameHeader::Varint, U64_BYTE) => Ok(9),
+            (FrameHeader::Varint, _) => {
+                Err(custom_error!("invalid first byte of a varint frame header: {}", first).into())
+            }
+            (header, _) => Ok(header.width(0)),
+        }
+    }
+
+    fn max_len(self) -> u64 {
+        match self {
+            FrameHeader::U16 => u16::MAX as u64,
+            FrameHeader::U32 => u32::MAX as u64,
+            FrameHeader::U64 | FrameHeader::Varint => u64::MAX,
+        }
+    }
+
+    /// Returns an error if a frame of `len` bytes is longer than the header allows.
+    pub(crate) fn check_len(self, len: u64) -> Result<()> {
+        if len > self.max_len() {
+            return Err(custom_error!(
+                "a frame of {} bytes does not fit in a {:?} length header",
+                len,
+                self
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Reads the length from a header of `width_from_first` bytes.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn read<E: ByteOrder>(self, buf: &[u8]) -> u64 {
+        match self {
+            FrameHeader::U16 => E::read_u16(buf) as u64,
+            FrameHeader::U32 => E::read_u32(buf) as u64,
+            FrameHeader::U64 => E::read_u64(buf),
+            FrameHeader::Varint => match buf[0] {
+                U16_BYTE => E::read_u16(&buf[1..]) as u64,
+                U32_BYTE => E::read_u32(&buf[1..]) as u64,
+                U64_BYTE => E::read_u64(&buf[1..]),
+                byte => byte as u64,
+            },
+        }
+    }
+
+    /// Writes `len` into a header of `width(len)` bytes.
+    pub(crate) fn write<E: ByteOrder>(self, buf: &mut [u8], len: u64) {
+        match self {
+            FrameHeader::U16 => E::write_u16(buf, len as u16),
+            FrameHeader::U32 => E::write_u32(buf, len as u32),
+            FrameHeader::U64 => E::write_u64(buf, len),
+            FrameHeader::Varint => match self.width(len) {
+                1 => buf[0] = len as u8,
+                3 => {
+                    buf[0] = U16_BYTE;
+                    E::write_u16(&mut buf[1..], len as u16);
+                }
+                5 => {
+                    buf[0] = U32_BYTE;
+                    E::write_u32(&mut buf[1..], len as u32);
+                }
+                _ => {
+                    buf[0] = U64_BYTE;
+                    E::write_u64(&mut buf[1..], len);
+                }
+            },
+        }
+    }
+}
+
+/// How `Options::serialize_framed` and `Options::deserialize_framed` frame a value.
+///
+/// ```
+/// # use bincode::{frame::{FrameHeader, Framing}, DefaultOptions, Options};
+/// let framing = Framing::new().with_header(FrameHeader::Varint).with_checksum();
+/// let mut snapshot = Vec::new();
+/// DefaultOptions::new().serialize_framed(framing, &mut snapshot, "hello").unwrap();
+/// let value: String = DefaultOptions::new().deserialize_framed(framing, &snapshot[..]).unwrap();
+/// assert_eq!(value, "hello");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Framing {
+    header: FrameHeader,
+    checksum: bool,
+}
+
+impl Framing {
+    /// Creates a framing with a `FrameHeader::U32` length and no checksum.
+    pub fn new() -> Framing {
+        Framing {
+            header: FrameHeader::U32,
+            checksum: false,
+        }
+    }
+
+    /// Sets the encoding of the length in front of every frame.
+    pub fn with_header(mut self, header: FrameHeader) -> Framing {
+        self.header = header;
+        self
+    }
+
+    /// Adds a CRC32C checksum of the payload after every frame.
+    pub fn with_checksum(mut self) -> Framing {
+        self.checksum = true;
+        self
+    }
+}
+
+impl Default for Framing {
+    fn default() -> Framing {
+        Framing::new()
+    }
+}
+
+/// The CRC32C (Castagnoli) lookup table.
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Continues the CRC32C checksum `crc` of some bytes with `bytes`. The checksum of no bytes
+/// is 0.
+pub(crate) fn crc32c(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in bytes {
+        crc = CRC32C_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// A `Write`r that computes the checksum of everything written through it.
+struct ChecksumWriter<W> {
+    writer: W,
+    crc: u32,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> crate::io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.crc = crc32c(self.crc, &buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> crate::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub(crate) fn serialize_framed<W, T, O>(
+    framing: Framing,
+    mut writer: W,
+    value: &T,
+    mut options: O,
+) -> Result<()>
+where
+    W: Write,
+    T: ?Sized + serde::Serialize,
+    O: Options,
+{
+    // fails if the value is larger than the size limit
+    let len = crate::internal::serialized_size(value, &mut options)?;
+    framing.header.check_len(len)?;
+
+    let mut header = [0u8; FrameHeader::MAX_WIDTH];
+    let width = framing.header.width(len);
+    framing
+        .header
+        .write::<<O::Endian as BincodeByteOrder>::Endian>(&mut header[..width], len);
+    writer.write_all(&header[..width])?;
+
+    let mut writer = ChecksumWriter { writer, crc: 0 };
+    crate::internal::serialize_into(&mut writer, value, options.with_no_limit())?;
+    if framing.checksum {
+        let mut trailer = [0u8; 4];
+        <O::Endian as BincodeByteOrder>::Endian::write_u32(&mut trailer, writer.crc);
+        writer.writer.write_all(&trailer)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) fn deserialize_framed<R, T, O>(
+    framing: Framing,
+    mut reader: R,
+    mut options: O,
+) -> Result<T>
+where
+    R: Read,
+    T: serde::de::DeserializeOwned,
+    O: Options,
+{
+    let mut header = [0u8; FrameHeader::MAX_WIDTH];
+    reader.read_exact(&mut header[..1])?;
+    let width = framing.header.width_from_first(header[0])?;
+    reader.read_exact(&mut header[1..width])?;
+    let len = framing
+        .header
+        .read::<<O::Endian as BincodeByteOrder>::Endian>(&header[..width]);
+    if let Some(limit) = options.limit().limit() {
+        if len > limit {
+            return Err(ErrorKind::SizeLimit.into());
+        }
+    }
+    if let Some(remaining) = options.alloc_limit().limit() {
+        if len > remaining {
+            return Err(ErrorKind::AllocationLimit {
+                requested: len,
+                remaining,
+            }
+            .into());
+        }
+    }
+    // a frame that does not fit in memory is treated like one that exceeds the limit
+    let len = usize::try_from(len).map_err(|_| ErrorKind::SizeLimit)?;
+
+    // the header is not trusted, so the payload is only allocated as it arrives, doubling with
+    // every read
+    let mut payload = Vec::new();
+    while payload.len() < len {
+        let start = payload.len();
+        payload.resize(start + (len - start).min(start.max(PAYLOAD_CHUNK)), 0);
+        reader.read_exact(&mut payload[start..])?;
+    }
+    if framing.checksum {
+        let mut trailer = [0u8; 4];
+        reader.read_exact(&mut trailer)?;
+        let expected = <O::Endian as BincodeByteOrder>::Endian::read_u32(&trailer);
+        let actual = crc32c(0, &payload);
+        if expected != actual {
+            return Err(ErrorKind::ChecksumMismatch { expected, actual }.into());
+        }
+    }
+    crate::internal::deserialize(&payload, options)
+}
+
+/// The size of the first read of a frame's payload.
+#[cfg(feature = "alloc")]
+const PAYLOAD_CHUNK: usize = 4096;
+
+#[cfg(test)]
+mod test {
+    use super::crc32c;
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(crc32c(0, b""), 0);
+        assert_eq!(crc32c(0, b"123456789"), 0xE306_9283);
+        assert_eq!(crc32c(crc32c(0, b"1234"), b"56789"), 0xE306_9283);
+    }
+}
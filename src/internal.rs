@@ -1,10 +1,15 @@
-use core2::io::{Read, Write};
+#[cfg(feature = "alloc")]
+use crate::io::Read;
+use crate::io::{Seek, Write};
 use core::marker::PhantomData;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
-use crate::config::{Infinite, InternalOptions, Options, SizeLimit, TrailingBytes};
+#[cfg(feature = "alloc")]
+use crate::config::Options;
+use crate::config::{Infinite, InternalOptions, SizeLimit, TrailingBytes};
 use crate::de::read::BincodeRead;
-use crate::Result;
+use crate::{ErrorKind, Result};
 
 pub(crate) fn serialize_into<W, T: ?Sized, O>(writer: W, value: &T, mut options: O) -> Result<()>
 where
@@ -22,6 +27,58 @@ where
     serde::Serialize::serialize(value, &mut serializer)
 }
 
+pub(crate) fn serialize_into_seekable<W, T, O>(writer: W, value: &T, mut options: O) -> Result<()>
+where
+    W: Write + Seek,
+    T: ?Sized + serde::Serialize,
+    O: InternalOptions,
+{
+    if options.limit().limit().is_some() {
+        // "compute" the size for the side-effect
+        // of returning Err if the bound was reached.
+        serialized_size(value, &mut options)?;
+    }
+
+    let mut serializer = crate::ser::Serializer::<_, O>::new_seekable(writer, options);
+    serde::Serialize::serialize(value, &mut serializer)
+}
+
+pub(crate) fn serialize_into_slice<T, O>(
+    slice: &mut [u8],
+    value: &T,
+    mut options: O,
+) -> Result<usize>
+where
+    T: ?Sized + serde::Serialize,
+    O: InternalOptions,
+{
+    let available = slice.len() as u64;
+    if options.limit().limit().is_some() {
+        // The size limit needs the size up front anyway,
+        // so check that it fits before writing anything.
+        let needed = serialized_size(value, &mut options)?;
+        if needed > available {
+            return Err(ErrorKind::BufferTooSmall { needed, available }.into());
+        }
+    }
+
+    let mut writer = crate::ser::SliceWriter::new(slice);
+    let result = {
+        let mut serializer = crate::ser::Serializer::new(&mut writer, &mut options);
+        serde::Serialize::serialize(value, &mut serializer)
+    };
+    match result {
+        Ok(()) => Ok(writer.written),
+        Err(_) if writer.overflowed => {
+            // Only pay for the size pre-pass once the value is known not to fit.
+            let needed = serialized_size(value, &mut options)?;
+            Err(ErrorKind::BufferTooSmall { needed, available }.into())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(feature = "alloc")]
 pub(crate) fn serialize<T: ?Sized, O>(value: &T, mut options: O) -> Result<Vec<u8>>
 where
     T: serde::Serialize,
@@ -40,12 +97,17 @@ pub(crate) fn serialized_size<T: ?Sized, O: InternalOptions>(value: &T, options:
 where
     T: serde::Serialize,
 {
-    let mut size_counter = crate::ser::SizeChecker { options, total: 0 };
+    let mut size_counter = crate::ser::SizeChecker {
+        options,
+        total: 0,
+        depth: 0,
+    };
 
     let result = value.serialize(&mut size_counter);
     result.map(|_| size_counter.total)
 }
 
+#[cfg(feature = "alloc")]
 pub(crate) fn deserialize_from<R, T, O>(reader: R, options: O) -> Result<T>
 where
     R: Read,
@@ -55,6 +117,7 @@ where
     deserialize_from_seed(PhantomData, reader, options)
 }
 
+#[cfg(feature = "alloc")]
 pub(crate) fn deserialize_from_seed<'a, R, T, O>(seed: T, reader: R, options: O) -> Result<T::Value>
 where
     R: Read,
@@ -86,6 +149,7 @@ where
 {
     let mut deserializer = crate::de::Deserializer::<_, O>::with_bincode_read(reader, options);
     seed.deserialize(&mut deserializer)
+        .map_err(|err| deserializer.with_error_context(err))
 }
 
 pub(crate) fn deserialize_in_place<'a, R, T, O>(reader: R, options: O, place: &mut T) -> Result<()>
@@ -96,6 +160,7 @@ where
 {
     let mut deserializer = crate::de::Deserializer::<_, _>::with_bincode_read(reader, options);
     serde::Deserialize::deserialize_in_place(&mut deserializer, place)
+        .map_err(|err| deserializer.with_error_context(err))
 }
 
 pub(crate) fn deserialize<'a, T, O>(bytes: &'a [u8], options: O) -> Result<T>
@@ -106,6 +171,20 @@ where
     deserialize_seed(PhantomData, bytes, options)
 }
 
+pub(crate) fn deserialize_partial<'a, T, O>(bytes: &'a [u8], options: O) -> Result<(T, &'a [u8])>
+where
+    T: serde::de::Deserialize<'a>,
+    O: InternalOptions,
+{
+    let options = crate::config::WithOtherLimit::new(options, Infinite);
+
+    let reader = crate::de::read::SliceReader::new(bytes);
+    let mut deserializer = crate::de::Deserializer::with_bincode_read(reader, options);
+    let val = serde::Deserialize::deserialize(&mut deserializer)
+        .map_err(|err| deserializer.with_error_context(err))?;
+    Ok((val, deserializer.reader.remaining()))
+}
+
 pub(crate) fn deserialize_seed<'a, T, O>(seed: T, bytes: &'a [u8], options: O) -> Result<T::Value>
 where
     T: serde::de::DeserializeSeed<'a>,
@@ -115,7 +194,9 @@ where
 
     let reader = crate::de::read::SliceReader::new(bytes);
     let mut deserializer = crate::de::Deserializer::with_bincode_read(reader, options);
-    let val = seed.deserialize(&mut deserializer)?;
+    let val = seed
+        .deserialize(&mut deserializer)
+        .map_err(|err| deserializer.with_error_context(err))?;
 
     match O::Trailing::check_end(&deserializer.reader) {
         Ok(_) => Ok(val),
@@ -20,6 +20,30 @@
 //! }
 //! ```
 //!
+//! ### Cargo features
+//!
+//! With the `std` feature, which is enabled by default, bincode reads from `std::io::Read` and
+//! writes to `std::io::Write`. Without it, bincode is `no_std` and only needs `alloc`. The
+//! `core2` feature then provides the `Read` and `Write` traits, which requires a nightly
+//! compiler; otherwise bincode uses the minimal traits in its own `io` module, which build on
+//! stable. The repository pins the stable toolchain, so `core2` builds of it are run with
+//! `cargo +nightly`.
+//!
+//! The `embedded-io` feature adds support for `embedded_io::Read` and `embedded_io::Write`
+//! through the [embedded](embedded/index.html) module, and the `tokio` feature adds a
+//! length-prefixed `tokio_util` codec in the [codec](codec/index.html) module. The `futures-io`
+//! feature adds `Options::serialize_into_async` and `Options::deserialize_from_async` for
+//! `futures_io::AsyncWrite` and `futures_io::AsyncRead`, which work with any async runtime.
+//! The `bytes` feature adds support for `bytes::Buf` and `bytes::BufMut` through the
+//! [buf](buf/index.html) module; `Bytes` fields read with [`buf::shared`](buf/shared/index.html)
+//! only share the memory of the input with `std`, and `serde_bytes` fields are not shared.
+//!
+//! Disabling the `alloc` feature (which `std`, `core2` and `embedded-io` enable) gives a
+//! no-alloc build for bootloaders and other targets with a tight heap. Values can then only be
+//! deserialized from slices, borrowing `&str` and `&[u8]` from the input, and `Error` is a
+//! `Copy` enum whose messages are all `&'static str`. Sequences and maps of unknown length are
+//! only supported by `serialize_into_seekable`, and errors carry no context.
+//!
 //! ### 128bit numbers
 //!
 //! Support for `i128` and `u128` is automatically enabled on Rust toolchains
@@ -29,20 +53,62 @@
 #![crate_name = "bincode"]
 #![crate_type = "rlib"]
 #![crate_type = "dylib"]
+// Without `alloc`, `Error` is `ErrorKind` itself, so the `.into()` that boxes an error is a no-op
+#![cfg_attr(not(feature = "alloc"), allow(clippy::useless_conversion))]
+// Lints that clippy gained after the code they flag was written
+#![allow(
+    clippy::doc_lazy_continuation,
+    clippy::extra_unused_lifetimes,
+    clippy::legacy_numeric_constants,
+    clippy::manual_is_multiple_of,
+    clippy::mem_replace_with_default,
+    clippy::multiple_bound_locations,
+    clippy::needless_doctest_main,
+    clippy::needless_lifetimes,
+    clippy::unnecessary_cast
+)]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(all(feature = "core2", not(feature = "std")))]
 extern crate core2;
 #[macro_use]
 extern crate serde;
+#[cfg(feature = "std")]
+extern crate std;
+
+// The I/O traits that bincode reads from and writes to, re-exported so that their errors can be
+// matched on the same way whichever feature provides them.
+#[cfg(all(feature = "core2", not(feature = "std")))]
+pub use core2::io;
+#[cfg(not(any(feature = "std", feature = "core2")))]
+pub mod io;
+#[cfg(feature = "std")]
+pub use std::io;
 
+#[macro_use]
+mod error;
+
+#[cfg(feature = "futures-io")]
+pub mod async_io;
+#[cfg(feature = "bytes")]
+pub mod buf;
+#[cfg(feature = "tokio")]
+pub mod codec;
 pub mod config;
 /// Deserialize bincode data to a Rust data structure.
 pub mod de;
+#[cfg(feature = "alloc")]
+pub mod decoder;
+#[cfg(feature = "embedded-io")]
+pub mod embedded;
+pub mod envelope;
+pub mod frame;
 
 mod byteorder;
-mod error;
 mod internal;
 mod ser;
+mod tag;
 
 pub use config::{Config, DefaultOptions, Options};
 pub use de::read::BincodeRead;
@@ -50,6 +116,7 @@ pub use de::Deserializer;
 pub use error::{Error, ErrorKind, Result};
 pub use ser::Serializer;
 
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
 /// Get a default configuration object.
@@ -94,7 +161,7 @@ pub fn options() -> DefaultOptions {
 /// module for more details
 pub fn serialize_into<W, T: ?Sized>(writer: W, value: &T) -> Result<()>
 where
-    W: core2::io::Write,
+    W: io::Write,
     T: serde::Serialize,
 {
     DefaultOptions::new()
@@ -108,6 +175,7 @@ where
 /// the same as that used by the `DefaultOptions` struct. See the
 /// [config](config/index.html#options-struct-vs-bincode-functions)
 /// module for more details
+#[cfg(feature = "alloc")]
 pub fn serialize<T: ?Sized>(value: &T) -> Result<Vec<u8>>
 where
     T: serde::Serialize,
@@ -126,9 +194,10 @@ where
 /// the same as that used by the `DefaultOptions` struct. See the
 /// [config](config/index.html#options-struct-vs-bincode-functions)
 /// module for more details
+#[cfg(feature = "alloc")]
 pub fn deserialize_from<R, T>(reader: R) -> Result<T>
 where
-    R: core2::io::Read,
+    R: io::Read,
     T: serde::de::DeserializeOwned,
 {
     DefaultOptions::new()
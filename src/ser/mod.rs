@@ -1,11 +1,17 @@
-use core2::io::Write;
+use crate::io::{self, Seek, SeekFrom, Write};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 use core::u32;
 
 use crate::byteorder::WriteBytesExt;
 
-use super::config::{IntEncoding, SizeLimit};
+use super::config::{
+    EnumTag, IntEncoding, LenEncoding, SelfDescribing, SizeLimit, StructEvolution, UnknownLength,
+    VariantEvolution,
+};
 use super::{Error, ErrorKind, Result};
 use crate::config::{BincodeByteOrder, Options};
+use crate::tag::Tag;
 use core::mem::size_of;
 
 /// An Serializer that encodes values directly into a Writer.
@@ -16,8 +22,112 @@ use core::mem::size_of;
 /// This struct should not be used often.
 /// For most cases, prefer the `encode_into` function.
 pub struct Serializer<W, O: Options> {
+    writer: Output<W>,
+    options: O,
+    /// The number of options, enums and collections enclosing the value being serialized.
+    depth: u64,
+}
+
+/// The writer behind a `Serializer`.
+///
+/// While a sequence or map of unknown length is being buffered, writes go to the innermost
+/// open buffer instead of the underlying writer. Without the `alloc` feature nothing is
+/// buffered, so the length of such a sequence can only be backpatched.
+struct Output<W> {
     writer: W,
-    _options: O,
+    #[cfg(feature = "alloc")]
+    buffers: Vec<Vec<u8>>,
+    seek: Option<fn(&mut W, SeekFrom) -> io::Result<u64>>,
+}
+
+impl<W: Write> Write for Output<W> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[cfg(feature = "alloc")]
+        if let Some(buffer) = self.buffers.last_mut() {
+            return buffer.write(buf);
+        }
+        self.writer.write(buf)
+    }
+
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        #[cfg(feature = "alloc")]
+        if let Some(buffer) = self.buffers.last_mut() {
+            return buffer.write_all(buf);
+        }
+        self.writer.write_all(buf)
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// A writer into a caller-provided slice, which remembers whether it ran out of space so that
+/// the resulting io error can be reported as `ErrorKind::BufferTooSmall`.
+pub(crate) struct SliceWriter<'a> {
+    slice: &'a mut [u8],
+    pub written: usize,
+    pub overflowed: bool,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(slice: &'a mut [u8]) -> SliceWriter<'a> {
+        SliceWriter {
+            slice,
+            written: 0,
+            overflowed: false,
+        }
+    }
+}
+
+impl<'a> Write for SliceWriter<'a> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let end = self.written + buf.len();
+        if end > self.slice.len() {
+            self.overflowed = true;
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to write whole buffer",
+            ));
+        }
+        self.slice[self.written..end].copy_from_slice(buf);
+        self.written = end;
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Bookkeeping for a sequence, map or struct whose length is written once it has ended.
+struct PendingLen {
+    count: usize,
+    /// Whether the length is the number of bytes written rather than the number of elements.
+    bytes: bool,
+    /// The position of the placeholder length if it is backpatched, or `None` if the
+    /// elements are buffered.
+    patch_at: Option<u64>,
+}
+
+/// Returns an error if `depth` is deeper than the depth limit of `options`.
+#[inline(always)]
+fn check_depth<O: Options>(options: &mut O, depth: u64) -> Result<()> {
+    match options.depth_limit().limit() {
+        Some(max) if depth > max => Err(ErrorKind::DepthLimitExceeded.into()),
+        _ => Ok(()),
+    }
 }
 
 macro_rules! impl_serialize_literal {
@@ -34,15 +144,185 @@ impl<W: Write, O: Options> Serializer<W, O> {
     /// Creates a new Serializer with the given `Write`r.
     pub fn new(w: W, options: O) -> Serializer<W, O> {
         Serializer {
-            writer: w,
-            _options: options,
+            writer: Output {
+                writer: w,
+                #[cfg(feature = "alloc")]
+                buffers: Vec::new(),
+                seek: None,
+            },
+            options,
+            depth: 0,
+        }
+    }
+
+    /// Creates a new Serializer with the given seekable `Write`r.
+    ///
+    /// Sequences and maps of unknown length have their length backpatched in place when the
+    /// length encoding has a fixed width, instead of being buffered.
+    pub fn new_seekable(w: W, options: O) -> Serializer<W, O>
+    where
+        W: Seek,
+    {
+        let mut serializer = Serializer::new(w, options);
+        serializer.writer.seek = Some(W::seek);
+        serializer
+    }
+
+    fn begin_unknown_len(&mut self) -> Result<PendingLen> {
+        if !O::UnknownLength::allowed() {
+            return Err(ErrorKind::SequenceMustHaveLength.into());
+        }
+
+        match self.begin_pending(false)? {
+            Some(pending) => Ok(pending),
+            None => Err(ErrorKind::SequenceMustHaveLength.into()),
+        }
+    }
+
+    /// Begins a value that is prefixed by its length in bytes, which is written once it has
+    /// ended.
+    fn begin_byte_len(&mut self) -> Result<PendingLen> {
+        match self.begin_pending(true)? {
+            Some(pending) => Ok(pending),
+            None => Err(custom_error!(
+                "a length in bytes can only be written without the alloc feature if the writer \
+                 is seekable and the length encoding has a fixed width"
+            )
+            .into()),
+        }
+    }
+
+    /// Begins the fields of a struct, which are prefixed by their length in bytes if struct
+    /// evolution is enabled.
+    fn begin_struct(&mut self) -> Result<Option<PendingLen>> {
+        if O::StructEvolution::enabled() && !O::SelfDescribing::enabled() {
+            self.begin_byte_len().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Begins the payload of an enum variant, which is prefixed by its length in bytes if variant
+    /// evolution is enabled.
+    fn begin_variant(&mut self) -> Result<Option<PendingLen>> {
+        if O::VariantEvolution::enabled() && !O::SelfDescribing::enabled() {
+            self.begin_byte_len().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn end_pending(&mut self, pending: Option<PendingLen>) -> Result<()> {
+        match pending {
+            Some(pending) => self.end_unknown_len(pending),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes a placeholder length to backpatch, or starts buffering, returning `None` if
+    /// neither is possible.
+    fn begin_pending(&mut self, bytes: bool) -> Result<Option<PendingLen>> {
+        match self.writer.seek {
+            Some(seek) if O::LenEncoding::len_is_fixed_width::<O>() => {
+                let position = seek(&mut self.writer.writer, SeekFrom::Current(0))?;
+                O::LenEncoding::serialize_len(self, 0)?;
+                Ok(Some(PendingLen {
+                    count: 0,
+                    bytes,
+                    patch_at: Some(position),
+                }))
+            }
+            #[cfg(feature = "alloc")]
+            _ => {
+                self.writer.buffers.push(Vec::new());
+                Ok(Some(PendingLen {
+                    count: 0,
+                    bytes,
+                    patch_at: None,
+                }))
+            }
+            #[cfg(not(feature = "alloc"))]
+            _ => Ok(None),
+        }
+    }
+
+    fn end_unknown_len(&mut self, pending: PendingLen) -> Result<()> {
+        match (pending.patch_at, self.writer.seek) {
+            (Some(position), Some(seek)) => {
+                let end = seek(&mut self.writer.writer, SeekFrom::Current(0))?;
+                let len = if pending.bytes {
+                    (end - position - O::LenEncoding::len_size::<O>(0)?) as usize
+                } else {
+                    pending.count
+                };
+                seek(&mut self.writer.writer, SeekFrom::Start(position))?;
+                O::LenEncoding::serialize_len(self, len)?;
+                seek(&mut self.writer.writer, SeekFrom::Start(end))?;
+                Ok(())
+            }
+            #[cfg(feature = "alloc")]
+            _ => {
+                let buffer = self
+                    .writer
+                    .buffers
+                    .pop()
+                    .expect("an unknown length sequence must have an open buffer");
+                let len = if pending.bytes {
+                    buffer.len()
+                } else {
+                    pending.count
+                };
+                O::LenEncoding::serialize_len(self, len)?;
+                self.writer.write_all(&buffer).map_err(Into::into)
+            }
+            #[cfg(not(feature = "alloc"))]
+            _ => {
+                unreachable!("an unknown length sequence is only begun when it can be backpatched")
+            }
         }
     }
 
+    /// Goes `levels` levels deeper, returning the depth to restore once they have ended.
+    fn enter(&mut self, levels: u64) -> Result<u64> {
+        let depth = self.depth;
+        self.depth += levels;
+        check_depth(&mut self.options, self.depth)?;
+        Ok(depth)
+    }
+
     pub(crate) fn serialize_byte(&mut self, v: u8) -> Result<()> {
         self.writer.write_u8(v).map_err(Into::into)
     }
 
+    /// Writes the tag of the value that follows, if the format is self-describing.
+    fn serialize_tag(&mut self, tag: Tag) -> Result<()> {
+        if O::SelfDescribing::enabled() {
+            self.serialize_byte(tag as u8)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the tag and length of a tuple or struct, which are implied by the type unless the
+    /// format is self-describing.
+    fn serialize_fixed_len(&mut self, tag: Tag, len: usize) -> Result<()> {
+        if O::SelfDescribing::enabled() {
+            self.serialize_byte(tag as u8)?;
+            O::LenEncoding::serialize_len(self, len)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the index of an enum variant, along with its name if the format is self-describing.
+    fn serialize_variant(&mut self, variant_index: u32, variant: &str) -> Result<()> {
+        self.serialize_tag(Tag::Enum)?;
+        O::EnumTag::serialize_tag(self, variant_index)?;
+        if O::SelfDescribing::enabled() {
+            O::LenEncoding::serialize_len(self, variant.len())?;
+            self.writer.write_all(variant.as_bytes())?;
+        }
+        Ok(())
+    }
+
     impl_serialize_literal! {serialize_literal_u16(u16) = write_u16()}
     impl_serialize_literal! {serialize_literal_u32(u32) = write_u32()}
     impl_serialize_literal! {serialize_literal_u64(u64) = write_u64()}
@@ -53,8 +333,9 @@ impl<W: Write, O: Options> Serializer<W, O> {
 }
 
 macro_rules! impl_serialize_int {
-    ($ser_method:ident($ty:ty) = $ser_int:ident()) => {
+    ($ser_method:ident($ty:ty) = $ser_int:ident(), $tag:expr) => {
         fn $ser_method(self, v: $ty) -> Result<()> {
+            self.serialize_tag($tag)?;
             O::IntEncoding::$ser_int(self, v)
         }
     };
@@ -72,67 +353,100 @@ impl<'a, W: Write, O: Options> serde::Serializer for &'a mut Serializer<W, O> {
     type SerializeStructVariant = Compound<'a, W, O>;
 
     fn serialize_unit(self) -> Result<()> {
-        Ok(())
+        self.serialize_tag(Tag::Unit)
     }
 
     fn serialize_unit_struct(self, _: &'static str) -> Result<()> {
-        Ok(())
+        self.serialize_tag(Tag::Unit)
     }
 
     fn serialize_bool(self, v: bool) -> Result<()> {
+        self.serialize_tag(Tag::Bool)?;
         self.serialize_byte(v as u8)
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_tag(Tag::U8)?;
         self.serialize_byte(v)
     }
 
-    impl_serialize_int! {serialize_u16(u16) = serialize_u16()}
-    impl_serialize_int! {serialize_u32(u32) = serialize_u32()}
-    impl_serialize_int! {serialize_u64(u64) = serialize_u64()}
+    impl_serialize_int! {serialize_u16(u16) = serialize_u16(), Tag::U16}
+    impl_serialize_int! {serialize_u32(u32) = serialize_u32(), Tag::U32}
+    impl_serialize_int! {serialize_u64(u64) = serialize_u64(), Tag::U64}
 
     fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_tag(Tag::I8)?;
         self.serialize_byte(v as u8)
     }
 
-    impl_serialize_int! {serialize_i16(i16) = serialize_i16()}
-    impl_serialize_int! {serialize_i32(i32) = serialize_i32()}
-    impl_serialize_int! {serialize_i64(i64) = serialize_i64()}
+    impl_serialize_int! {serialize_i16(i16) = serialize_i16(), Tag::I16}
+    impl_serialize_int! {serialize_i32(i32) = serialize_i32(), Tag::I32}
+    impl_serialize_int! {serialize_i64(i64) = serialize_i64(), Tag::I64}
 
     serde_if_integer128! {
-        impl_serialize_int!{serialize_u128(u128) = serialize_u128()}
-        impl_serialize_int!{serialize_i128(i128) = serialize_i128()}
+        impl_serialize_int!{serialize_u128(u128) = serialize_u128(), Tag::U128}
+        impl_serialize_int!{serialize_i128(i128) = serialize_i128(), Tag::I128}
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_tag(Tag::F32)?;
         self.writer
             .write_f32::<<O::Endian as BincodeByteOrder>::Endian>(v)
             .map_err(Into::into)
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
+        self.serialize_tag(Tag::F64)?;
         self.writer
             .write_f64::<<O::Endian as BincodeByteOrder>::Endian>(v)
             .map_err(Into::into)
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        O::IntEncoding::serialize_len(self, v.len())?;
+        self.serialize_tag(Tag::Str)?;
+        O::LenEncoding::serialize_len(self, v.len())?;
         self.writer.write_all(v.as_bytes()).map_err(Into::into)
     }
 
+    /// Without the `alloc` feature the value is formatted twice, once to find its length and
+    /// once to write it, instead of into a temporary `String`.
+    #[cfg(not(feature = "alloc"))]
+    fn collect_str<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + core::fmt::Display,
+    {
+        self.serialize_tag(Tag::Str)?;
+        O::LenEncoding::serialize_len(self, display_len(value))?;
+        let mut out = FmtWriter {
+            writer: &mut self.writer,
+            error: None,
+        };
+        if core::fmt::write(&mut out, format_args!("{}", value)).is_err() {
+            return Err(match out.error {
+                Some(error) => error.into(),
+                None => ErrorKind::Custom("a Display implementation returned an error").into(),
+            });
+        }
+        Ok(())
+    }
+
     fn serialize_char(self, c: char) -> Result<()> {
+        self.serialize_tag(Tag::Char)?;
         self.writer
             .write_all(encode_utf8(c).as_slice())
             .map_err(Into::into)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        O::IntEncoding::serialize_len(self, v.len())?;
+        self.serialize_tag(Tag::Bytes)?;
+        O::LenEncoding::serialize_len(self, v.len())?;
         self.writer.write_all(v).map_err(Into::into)
     }
 
     fn serialize_none(self) -> Result<()> {
+        if O::SelfDescribing::enabled() {
+            return self.serialize_tag(Tag::None);
+        }
         self.writer.write_u8(0).map_err(Into::into)
     }
 
@@ -140,58 +454,126 @@ impl<'a, W: Write, O: Options> serde::Serializer for &'a mut Serializer<W, O> {
     where
         T: serde::Serialize,
     {
-        self.writer.write_u8(1)?;
-        v.serialize(self)
+        let depth = self.enter(1)?;
+        if O::SelfDescribing::enabled() {
+            self.serialize_tag(Tag::Some)?;
+        } else {
+            self.writer.write_u8(1)?;
+        }
+        v.serialize(&mut *self)?;
+        self.depth = depth;
+        Ok(())
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        let len = len.ok_or(ErrorKind::SequenceMustHaveLength)?;
-        O::IntEncoding::serialize_len(self, len)?;
-        Ok(Compound { ser: self })
-    }
-
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Ok(Compound { ser: self })
+        let depth = self.enter(1)?;
+        self.serialize_tag(Tag::Seq)?;
+        let pending = match len {
+            Some(len) => {
+                O::LenEncoding::serialize_len(self, len)?;
+                None
+            }
+            None => Some(self.begin_unknown_len()?),
+        };
+        Ok(Compound {
+            ser: self,
+            pending,
+            depth,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        let depth = self.enter(1)?;
+        self.serialize_fixed_len(Tag::Seq, len)?;
+        Ok(Compound {
+            ser: self,
+            pending: None,
+            depth,
+        })
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Ok(Compound { ser: self })
+        let depth = self.enter(1)?;
+        self.serialize_fixed_len(Tag::Seq, len)?;
+        Ok(Compound {
+            ser: self,
+            pending: None,
+            depth,
+        })
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        O::IntEncoding::serialize_u32(self, variant_index)?;
-        Ok(Compound { ser: self })
+        // one level for the enum and one for the fields of the variant
+        let depth = self.enter(2)?;
+        self.serialize_variant(variant_index, variant)?;
+        let pending = self.begin_variant()?;
+        self.serialize_fixed_len(Tag::Seq, len)?;
+        Ok(Compound {
+            ser: self,
+            pending,
+            depth,
+        })
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        let len = len.ok_or(ErrorKind::SequenceMustHaveLength)?;
-        O::IntEncoding::serialize_len(self, len)?;
-        Ok(Compound { ser: self })
-    }
-
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        Ok(Compound { ser: self })
+        let depth = self.enter(1)?;
+        self.serialize_tag(Tag::Map)?;
+        let pending = match len {
+            Some(len) => {
+                O::LenEncoding::serialize_len(self, len)?;
+                None
+            }
+            None => Some(self.begin_unknown_len()?),
+        };
+        Ok(Compound {
+            ser: self,
+            pending,
+            depth,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        let depth = self.enter(1)?;
+        self.serialize_fixed_len(Tag::Map, len)?;
+        let pending = self.begin_struct()?;
+        Ok(Compound {
+            ser: self,
+            pending,
+            depth,
+        })
     }
 
     fn serialize_struct_variant(
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        O::IntEncoding::serialize_u32(self, variant_index)?;
-        Ok(Compound { ser: self })
+        // one level for the enum and one for the fields of the variant
+        let depth = self.enter(2)?;
+        self.serialize_variant(variant_index, variant)?;
+        // the length of the variant already delimits its fields
+        let pending = match self.begin_variant()? {
+            Some(pending) => Some(pending),
+            None => self.begin_struct()?,
+        };
+        self.serialize_fixed_len(Tag::Map, len)?;
+        Ok(Compound {
+            ser: self,
+            pending,
+            depth,
+        })
     }
 
     fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
@@ -205,23 +587,32 @@ impl<'a, W: Write, O: Options> serde::Serializer for &'a mut Serializer<W, O> {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &T,
     ) -> Result<()>
     where
         T: serde::ser::Serialize,
     {
-        O::IntEncoding::serialize_u32(self, variant_index)?;
-        value.serialize(self)
+        let depth = self.enter(1)?;
+        self.serialize_variant(variant_index, variant)?;
+        let pending = self.begin_variant()?;
+        value.serialize(&mut *self)?;
+        self.end_pending(pending)?;
+        self.depth = depth;
+        Ok(())
     }
 
     fn serialize_unit_variant(
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<()> {
-        O::IntEncoding::serialize_u32(self, variant_index)
+        self.depth = self.enter(1)?;
+        self.serialize_variant(variant_index, variant)?;
+        let pending = self.begin_variant()?;
+        self.serialize_tag(Tag::Unit)?;
+        self.end_pending(pending)
     }
 
     fn is_human_readable(&self) -> bool {
@@ -232,6 +623,7 @@ impl<'a, W: Write, O: Options> serde::Serializer for &'a mut Serializer<W, O> {
 pub(crate) struct SizeChecker<O: Options> {
     pub options: O,
     pub total: u64,
+    pub depth: u64,
 }
 
 impl<O: Options> SizeChecker<O> {
@@ -242,20 +634,98 @@ impl<O: Options> SizeChecker<O> {
         Ok(())
     }
 
+    /// Goes `levels` levels deeper, returning the depth to restore once they have ended.
+    fn enter(&mut self, levels: u64) -> Result<u64> {
+        let depth = self.depth;
+        self.depth += levels;
+        check_depth(&mut self.options, self.depth)?;
+        Ok(depth)
+    }
+
     fn add_discriminant(&mut self, idx: u32) -> Result<()> {
-        let bytes = O::IntEncoding::u32_size(idx);
+        let bytes = O::EnumTag::tag_size::<O>(idx)?;
         self.add_raw(bytes)
     }
 
+    fn add_tag(&mut self) -> Result<()> {
+        if O::SelfDescribing::enabled() {
+            self.add_raw(1)?;
+        }
+        Ok(())
+    }
+
+    fn add_fixed_len(&mut self, len: usize) -> Result<()> {
+        if O::SelfDescribing::enabled() {
+            self.add_raw(1)?;
+            self.add_len(len)?;
+        }
+        Ok(())
+    }
+
+    fn add_variant(&mut self, idx: u32, variant: &str) -> Result<()> {
+        self.add_tag()?;
+        self.add_discriminant(idx)?;
+        if O::SelfDescribing::enabled() {
+            self.add_len(variant.len())?;
+            self.add_raw(variant.len() as u64)?;
+        }
+        Ok(())
+    }
+
     fn add_len(&mut self, len: usize) -> Result<()> {
-        let bytes = O::IntEncoding::len_size(len);
+        let bytes = O::LenEncoding::len_size::<O>(len)?;
         self.add_raw(bytes)
     }
+
+    fn begin_len(&mut self, len: Option<usize>) -> Result<Option<usize>> {
+        match len {
+            Some(len) => {
+                self.add_len(len)?;
+                Ok(None)
+            }
+            None if O::UnknownLength::allowed() => Ok(Some(0)),
+            None => Err(ErrorKind::SequenceMustHaveLength.into()),
+        }
+    }
+
+    /// Begins the fields of a struct, returning the size so far if they are prefixed by their
+    /// length in bytes.
+    fn begin_struct(&self) -> Option<usize> {
+        if O::StructEvolution::enabled() && !O::SelfDescribing::enabled() {
+            Some(self.total as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Begins the payload of an enum variant, returning the size so far if it is prefixed by
+    /// its length in bytes.
+    fn begin_variant(&self) -> Option<usize> {
+        if O::VariantEvolution::enabled() && !O::SelfDescribing::enabled() {
+            Some(self.total as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Adds the length in bytes of a value that began when the size was `start`.
+    fn end_byte_len(&mut self, start: usize) -> Result<()> {
+        let len = self.total as usize - start;
+        self.add_len(len)
+    }
+
+    fn end_pending(&mut self, start: Option<usize>) -> Result<()> {
+        match start {
+            Some(start) => self.end_byte_len(start),
+            None => Ok(()),
+        }
+    }
 }
 
 macro_rules! impl_size_int {
     ($ser_method:ident($ty:ty) = $size_method:ident()) => {
         fn $ser_method(self, v: $ty) -> Result<()> {
+            self.add_tag()?;
             self.add_raw(O::IntEncoding::$size_method(v))
         }
     };
@@ -273,21 +743,24 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
     type SerializeStructVariant = SizeCompound<'a, O>;
 
     fn serialize_unit(self) -> Result<()> {
-        Ok(())
+        self.add_tag()
     }
 
     fn serialize_unit_struct(self, _: &'static str) -> Result<()> {
-        Ok(())
+        self.add_tag()
     }
 
     fn serialize_bool(self, _: bool) -> Result<()> {
+        self.add_tag()?;
         self.add_raw(1)
     }
 
     fn serialize_u8(self, _: u8) -> Result<()> {
+        self.add_tag()?;
         self.add_raw(1)
     }
     fn serialize_i8(self, _: i8) -> Result<()> {
+        self.add_tag()?;
         self.add_raw(1)
     }
 
@@ -304,23 +777,39 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
     }
 
     fn serialize_f32(self, _: f32) -> Result<()> {
+        self.add_tag()?;
         self.add_raw(size_of::<f32>() as u64)
     }
 
     fn serialize_f64(self, _: f64) -> Result<()> {
+        self.add_tag()?;
         self.add_raw(size_of::<f64>() as u64)
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
+        self.add_tag()?;
         self.add_len(v.len())?;
         self.add_raw(v.len() as u64)
     }
 
+    #[cfg(not(feature = "alloc"))]
+    fn collect_str<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + core::fmt::Display,
+    {
+        let len = display_len(value);
+        self.add_tag()?;
+        self.add_len(len)?;
+        self.add_raw(len as u64)
+    }
+
     fn serialize_char(self, c: char) -> Result<()> {
+        self.add_tag()?;
         self.add_raw(encode_utf8(c).as_slice().len() as u64)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.add_tag()?;
         self.add_len(v.len())?;
         self.add_raw(v.len() as u64)
     }
@@ -333,60 +822,107 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
     where
         T: serde::Serialize,
     {
+        let depth = self.enter(1)?;
         self.add_raw(1)?;
-        v.serialize(self)
+        v.serialize(&mut *self)?;
+        self.depth = depth;
+        Ok(())
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        let len = len.ok_or(ErrorKind::SequenceMustHaveLength)?;
-
-        self.add_len(len)?;
-        Ok(SizeCompound { ser: self })
-    }
-
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Ok(SizeCompound { ser: self })
+        let depth = self.enter(1)?;
+        self.add_tag()?;
+        let pending = self.begin_len(len)?;
+        Ok(SizeCompound {
+            ser: self,
+            pending,
+            depth,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        let depth = self.enter(1)?;
+        self.add_fixed_len(len)?;
+        Ok(SizeCompound {
+            ser: self,
+            pending: None,
+            depth,
+        })
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Ok(SizeCompound { ser: self })
+        let depth = self.enter(1)?;
+        self.add_fixed_len(len)?;
+        Ok(SizeCompound {
+            ser: self,
+            pending: None,
+            depth,
+        })
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.add_raw(O::IntEncoding::u32_size(variant_index))?;
-        Ok(SizeCompound { ser: self })
+        // one level for the enum and one for the fields of the variant
+        let depth = self.enter(2)?;
+        self.add_variant(variant_index, variant)?;
+        let pending = self.begin_variant();
+        self.add_fixed_len(len)?;
+        Ok(SizeCompound {
+            ser: self,
+            pending,
+            depth,
+        })
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        let len = len.ok_or(ErrorKind::SequenceMustHaveLength)?;
-
-        self.add_len(len)?;
-        Ok(SizeCompound { ser: self })
-    }
-
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        Ok(SizeCompound { ser: self })
+        let depth = self.enter(1)?;
+        self.add_tag()?;
+        let pending = self.begin_len(len)?;
+        Ok(SizeCompound {
+            ser: self,
+            pending,
+            depth,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        let depth = self.enter(1)?;
+        self.add_fixed_len(len)?;
+        let pending = self.begin_struct();
+        Ok(SizeCompound {
+            ser: self,
+            pending,
+            depth,
+        })
     }
 
     fn serialize_struct_variant(
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        self.add_discriminant(variant_index)?;
-        Ok(SizeCompound { ser: self })
+        // one level for the enum and one for the fields of the variant
+        let depth = self.enter(2)?;
+        self.add_variant(variant_index, variant)?;
+        // the length of the variant already delimits its fields
+        let pending = self.begin_variant().or_else(|| self.begin_struct());
+        self.add_fixed_len(len)?;
+        Ok(SizeCompound {
+            ser: self,
+            pending,
+            depth,
+        })
     }
 
     fn serialize_newtype_struct<V: serde::Serialize + ?Sized>(
@@ -401,20 +937,29 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<()> {
-        self.add_discriminant(variant_index)
+        self.depth = self.enter(1)?;
+        self.add_variant(variant_index, variant)?;
+        let pending = self.begin_variant();
+        self.add_tag()?;
+        self.end_pending(pending)
     }
 
     fn serialize_newtype_variant<V: serde::Serialize + ?Sized>(
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &V,
     ) -> Result<()> {
-        self.add_discriminant(variant_index)?;
-        value.serialize(self)
+        let depth = self.enter(1)?;
+        self.add_variant(variant_index, variant)?;
+        let pending = self.begin_variant();
+        value.serialize(&mut *self)?;
+        self.end_pending(pending)?;
+        self.depth = depth;
+        Ok(())
     }
 
     fn is_human_readable(&self) -> bool {
@@ -424,6 +969,9 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
 
 pub struct Compound<'a, W: 'a, O: Options + 'a> {
     ser: &'a mut Serializer<W, O>,
+    pending: Option<PendingLen>,
+    /// The depth of the serializer to restore once the compound value has ended.
+    depth: u64,
 }
 
 impl<'a, W, O> serde::ser::SerializeSeq for Compound<'a, W, O>
@@ -439,12 +987,19 @@ where
     where
         T: serde::ser::Serialize,
     {
+        if let Some(ref mut pending) = self.pending {
+            pending.count += 1;
+        }
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        match self.pending {
+            Some(pending) => self.ser.end_unknown_len(pending),
+            None => Ok(()),
+        }
     }
 }
 
@@ -466,6 +1021,7 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
+        self.ser.depth = self.depth;
         Ok(())
     }
 }
@@ -488,6 +1044,7 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
+        self.ser.depth = self.depth;
         Ok(())
     }
 }
@@ -510,7 +1067,8 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        self.ser.end_pending(self.pending)
     }
 }
 
@@ -527,6 +1085,9 @@ where
     where
         K: serde::ser::Serialize,
     {
+        if let Some(ref mut pending) = self.pending {
+            pending.count += 1;
+        }
         value.serialize(&mut *self.ser)
     }
 
@@ -540,7 +1101,11 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        match self.pending {
+            Some(pending) => self.ser.end_unknown_len(pending),
+            None => Ok(()),
+        }
     }
 }
 
@@ -553,16 +1118,23 @@ where
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: serde::ser::Serialize,
     {
+        if O::SelfDescribing::enabled() {
+            serde::Serializer::serialize_str(&mut *self.ser, key)?;
+        }
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        match self.pending {
+            Some(pending) => self.ser.end_unknown_len(pending),
+            None => Ok(()),
+        }
     }
 }
 
@@ -575,21 +1147,33 @@ where
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: serde::ser::Serialize,
     {
+        if O::SelfDescribing::enabled() {
+            serde::Serializer::serialize_str(&mut *self.ser, key)?;
+        }
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        match self.pending {
+            Some(pending) => self.ser.end_unknown_len(pending),
+            None => Ok(()),
+        }
     }
 }
 
 pub(crate) struct SizeCompound<'a, S: Options + 'a> {
     ser: &'a mut SizeChecker<S>,
+    /// The number of elements seen so far in a sequence or map of unknown length, or the size
+    /// before the fields of a struct that are prefixed by their length in bytes.
+    pending: Option<usize>,
+    /// The depth of the size checker to restore once the compound value has ended.
+    depth: u64,
 }
 
 impl<'a, O: Options> serde::ser::SerializeSeq for SizeCompound<'a, O> {
@@ -601,12 +1185,19 @@ impl<'a, O: Options> serde::ser::SerializeSeq for SizeCompound<'a, O> {
     where
         T: serde::ser::Serialize,
     {
+        if let Some(ref mut count) = self.pending {
+            *count += 1;
+        }
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        match self.pending {
+            Some(count) => self.ser.add_len(count),
+            None => Ok(()),
+        }
     }
 }
 
@@ -624,6 +1215,7 @@ impl<'a, O: Options> serde::ser::SerializeTuple for SizeCompound<'a, O> {
 
     #[inline]
     fn end(self) -> Result<()> {
+        self.ser.depth = self.depth;
         Ok(())
     }
 }
@@ -642,6 +1234,7 @@ impl<'a, O: Options> serde::ser::SerializeTupleStruct for SizeCompound<'a, O> {
 
     #[inline]
     fn end(self) -> Result<()> {
+        self.ser.depth = self.depth;
         Ok(())
     }
 }
@@ -660,7 +1253,8 @@ impl<'a, O: Options> serde::ser::SerializeTupleVariant for SizeCompound<'a, O> {
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        self.ser.end_pending(self.pending)
     }
 }
 
@@ -673,6 +1267,9 @@ impl<'a, O: Options + 'a> serde::ser::SerializeMap for SizeCompound<'a, O> {
     where
         K: serde::ser::Serialize,
     {
+        if let Some(ref mut count) = self.pending {
+            *count += 1;
+        }
         value.serialize(&mut *self.ser)
     }
 
@@ -686,7 +1283,11 @@ impl<'a, O: Options + 'a> serde::ser::SerializeMap for SizeCompound<'a, O> {
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        match self.pending {
+            Some(count) => self.ser.add_len(count),
+            None => Ok(()),
+        }
     }
 }
 
@@ -695,16 +1296,23 @@ impl<'a, O: Options> serde::ser::SerializeStruct for SizeCompound<'a, O> {
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: serde::ser::Serialize,
     {
+        if O::SelfDescribing::enabled() {
+            serde::Serializer::serialize_str(&mut *self.ser, key)?;
+        }
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        match self.pending {
+            Some(start) => self.ser.end_byte_len(start),
+            None => Ok(()),
+        }
     }
 }
 
@@ -713,16 +1321,23 @@ impl<'a, O: Options> serde::ser::SerializeStructVariant for SizeCompound<'a, O>
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: serde::ser::Serialize,
     {
+        if O::SelfDescribing::enabled() {
+            serde::Serializer::serialize_str(&mut *self.ser, key)?;
+        }
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.ser.depth = self.depth;
+        match self.pending {
+            Some(start) => self.ser.end_byte_len(start),
+            None => Ok(()),
+        }
     }
 }
 const TAG_CONT: u8 = 0b1000_0000;
@@ -733,6 +1348,40 @@ const MAX_ONE_B: u32 = 0x80;
 const MAX_TWO_B: u32 = 0x800;
 const MAX_THREE_B: u32 = 0x10000;
 
+/// Returns the length in bytes of `value` when formatted with `Display`.
+#[cfg(not(feature = "alloc"))]
+fn display_len<T: ?Sized + core::fmt::Display>(value: &T) -> usize {
+    struct Counter(usize);
+
+    impl core::fmt::Write for Counter {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            self.0 += s.len();
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    let _ = core::fmt::write(&mut counter, format_args!("{}", value));
+    counter.0
+}
+
+/// Writes formatted text to a writer, keeping the io error that `fmt::Error` can not carry.
+#[cfg(not(feature = "alloc"))]
+struct FmtWriter<'a, W> {
+    writer: &'a mut W,
+    error: Option<io::Error>,
+}
+
+#[cfg(not(feature = "alloc"))]
+impl<'a, W: Write> core::fmt::Write for FmtWriter<'a, W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            core::fmt::Error
+        })
+    }
+}
+
 fn encode_utf8(c: char) -> EncodeUtf8 {
     let code = c as u32;
     let mut buf = [0; 4];
@@ -768,3 +1417,52 @@ impl EncodeUtf8 {
         &self.buf[self.pos..]
     }
 }
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use crate::io::Cursor;
+    use crate::{DefaultOptions, Options};
+    use alloc::vec::Vec;
+
+    struct Evens(Vec<u64>);
+
+    impl serde::Serialize for Evens {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.0.iter().filter(|n| *n % 2 == 0))
+        }
+    }
+
+    #[test]
+    fn test_backpatch_unknown_length() {
+        let value = alloc::vec![Evens((0..10).collect()), Evens(alloc::vec![1, 3])];
+        let options = DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_unknown_lengths();
+        let buffered = options.serialize(&value).unwrap();
+
+        let mut out = [0u8; 128];
+        let mut cursor = Cursor::new(&mut out[..]);
+        options
+            .serialize_into_seekable(&mut cursor, &value)
+            .unwrap();
+        let written = cursor.position() as usize;
+
+        assert_eq!(&out[..written], &buffered[..]);
+    }
+
+    #[test]
+    fn test_seekable_varint_falls_back_to_buffering() {
+        let value = Evens((0..1000).collect());
+        let options = DefaultOptions::new().allow_unknown_lengths();
+        let buffered = options.serialize(&value).unwrap();
+
+        let mut out = [0u8; 4096];
+        let mut cursor = Cursor::new(&mut out[..]);
+        options
+            .serialize_into_seekable(&mut cursor, &value)
+            .unwrap();
+        let written = cursor.position() as usize;
+
+        assert_eq!(&out[..written], &buffered[..]);
+    }
+}
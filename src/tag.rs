@@ -0,0 +1,61 @@
+/// The tag written before each value when the format is self-describing.
+///
+/// Tuples and structs are written as `Seq` and `Map` respectively, with struct fields keyed by
+/// their names. An `Enum` tag is followed by the variant index, the variant name and then the
+/// variant's content as a tagged value, which is `Unit` for unit variants.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Tag {
+    Unit = 0,
+    Bool = 1,
+    U8 = 2,
+    U16 = 3,
+    U32 = 4,
+    U64 = 5,
+    U128 = 6,
+    I8 = 7,
+    I16 = 8,
+    I32 = 9,
+    I64 = 10,
+    I128 = 11,
+    F32 = 12,
+    F64 = 13,
+    Char = 14,
+    Str = 15,
+    Bytes = 16,
+    None = 17,
+    Some = 18,
+    Seq = 19,
+    Map = 20,
+    Enum = 21,
+}
+
+impl Tag {
+    pub(crate) fn from_u8(tag: u8) -> Option<Tag> {
+        let tag = match tag {
+            0 => Tag::Unit,
+            1 => Tag::Bool,
+            2 => Tag::U8,
+            3 => Tag::U16,
+            4 => Tag::U32,
+            5 => Tag::U64,
+            6 => Tag::U128,
+            7 => Tag::I8,
+            8 => Tag::I16,
+            9 => Tag::I32,
+            10 => Tag::I64,
+            11 => Tag::I128,
+            12 => Tag::F32,
+            13 => Tag::F64,
+            14 => Tag::Char,
+            15 => Tag::Str,
+            16 => Tag::Bytes,
+            17 => Tag::None,
+            18 => Tag::Some,
+            19 => Tag::Seq,
+            20 => Tag::Map,
+            21 => Tag::Enum,
+            _ => return None,
+        };
+        Some(tag)
+    }
+}
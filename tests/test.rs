@@ -1,3 +1,6 @@
+// These tests serialize into `Vec`s and strings, which needs the `alloc` feature.
+#![cfg(feature = "alloc")]
+
 #[macro_use]
 extern crate serde_derive;
 
@@ -11,9 +14,10 @@ use std::collections::HashMap;
 use std::fmt::{self, Debug};
 use std::result::Result as StdResult;
 
+use bincode::de::{OrUnknown, UnknownVariant};
 use bincode::{
-    deserialize, deserialize_from, deserialize_in_place, serialize, serialized_size,
-    DefaultOptions, ErrorKind, Options, Result,
+    deserialize, deserialize_from, serialize, serialized_size, DefaultOptions, ErrorKind, Options,
+    Result,
 };
 use serde::de::{Deserialize, DeserializeSeed, Deserializer, SeqAccess, Visitor};
 
@@ -57,7 +61,14 @@ where
         };
     }
 
-    all_integer_encodings!(element, DefaultOptions::new());
+    macro_rules! all_self_describing {
+        ($element:expr, $options:expr) => {
+            all_integer_encodings!($element, $options.without_self_describing());
+            all_integer_encodings!($element, $options.with_self_describing());
+        };
+    }
+
+    all_self_describing!(element, DefaultOptions::new());
 }
 
 #[test]
@@ -531,6 +542,7 @@ fn test_multi_strings_serialize() {
     assert!(serialize(&("foo", "bar", "baz")).is_ok());
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_oom_protection() {
     use std::io::Cursor;
@@ -607,6 +619,7 @@ fn test_zero_copy_parse() {
     }
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_zero_copy_parse_deserialize_into() {
     use bincode::BincodeRead;
@@ -619,8 +632,8 @@ fn test_zero_copy_parse_deserialize_into() {
 
     impl<'storage> SliceReader<'storage> {
         #[inline(always)]
-        fn unexpected_eof() -> Box<::ErrorKind> {
-            return Box::new(::ErrorKind::Io(io::Error::new(
+        fn unexpected_eof() -> Box<ErrorKind> {
+            return Box::new(ErrorKind::Io(io::Error::new(
                 io::ErrorKind::UnexpectedEof,
                 "",
             )));
@@ -701,7 +714,7 @@ fn test_zero_copy_parse_deserialize_into() {
             borrowed_str: "hello",
             borrowed_bytes: &[10, 11, 12, 13],
         };
-        deserialize_in_place(
+        bincode::deserialize_in_place(
             SliceReader {
                 slice: &encoded[..],
             },
@@ -897,3 +910,1823 @@ fn test_byte_vec_struct() {
 
     the_same(byte_struct);
 }
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct EvenNumbers(Vec<u32>);
+
+impl serde::Serialize for EvenNumbers {
+    fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        // `Filter` has no exact size hint, so serde passes an unknown length
+        serializer.collect_seq(self.0.iter().filter(|n| *n % 2 == 0))
+    }
+}
+
+#[test]
+fn test_unknown_length_rejected_by_default() {
+    let evens = EvenNumbers(vec![1, 2, 3, 4]);
+
+    match *DefaultOptions::new().serialize(&evens).unwrap_err() {
+        ErrorKind::SequenceMustHaveLength => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    match *DefaultOptions::new().serialized_size(&evens).unwrap_err() {
+        ErrorKind::SequenceMustHaveLength => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_unknown_length_seq() {
+    let evens = EvenNumbers((0..600).collect());
+    let expected: Vec<u32> = (0..600).filter(|n| n % 2 == 0).collect();
+
+    macro_rules! check {
+        ($options:expr) => {
+            let encoded = $options.allow_unknown_lengths().serialize(&evens).unwrap();
+            assert_eq!(encoded, $options.serialize(&expected).unwrap());
+            assert_eq!(
+                $options
+                    .allow_unknown_lengths()
+                    .serialized_size(&evens)
+                    .unwrap(),
+                encoded.len() as u64
+            );
+            let decoded: Vec<u32> = $options.deserialize(&encoded).unwrap();
+            assert_eq!(decoded, expected);
+
+            let mut writer = Vec::new();
+            $options
+                .allow_unknown_lengths()
+                .serialize_into(&mut writer, &evens)
+                .unwrap();
+            assert_eq!(writer, encoded);
+        };
+    }
+
+    check!(DefaultOptions::new().with_varint_encoding());
+    check!(DefaultOptions::new().with_fixint_encoding());
+    check!(DefaultOptions::new().with_big_endian());
+}
+
+#[test]
+fn test_unknown_length_nested() {
+    let nested = vec![
+        EvenNumbers(vec![1, 2, 3, 4]),
+        EvenNumbers(vec![]),
+        EvenNumbers(vec![6; 300]),
+    ];
+    let expected = vec![vec![2u32, 4], vec![], vec![6; 300]];
+
+    let options = DefaultOptions::new().allow_unknown_lengths();
+    let encoded = options.serialize(&nested).unwrap();
+    assert_eq!(encoded, DefaultOptions::new().serialize(&expected).unwrap());
+    assert_eq!(
+        options.serialized_size(&nested).unwrap(),
+        encoded.len() as u64
+    );
+}
+
+#[test]
+fn test_unknown_length_flatten() {
+    #[derive(Serialize)]
+    struct Inner {
+        a: u8,
+        b: String,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        id: u8,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+
+    let value = Outer {
+        id: 7,
+        inner: Inner {
+            a: 1,
+            b: "x".to_string(),
+        },
+    };
+    let options = DefaultOptions::new().allow_unknown_lengths();
+    let encoded = options.serialize(&value).unwrap();
+
+    // a flattened struct is serialized as a map keyed by field name
+    let mut expected = vec![3];
+    expected.extend_from_slice(&options.serialize(&("id", 7u8)).unwrap());
+    expected.extend_from_slice(&options.serialize(&("a", 1u8)).unwrap());
+    expected.extend_from_slice(&options.serialize(&("b", "x")).unwrap());
+    assert_eq!(encoded, expected);
+    assert_eq!(
+        options.serialized_size(&value).unwrap(),
+        encoded.len() as u64
+    );
+}
+
+#[test]
+fn test_unknown_length_respects_limit() {
+    let evens = EvenNumbers(vec![2; 100]);
+    let options = DefaultOptions::new().allow_unknown_lengths().with_limit(50);
+
+    assert!(options.serialize(&evens).is_err());
+    assert!(options.serialize_into(Vec::new(), &evens).is_err());
+}
+
+#[test]
+fn test_error_context() {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Signature {
+        verified: bool,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Transaction {
+        fee: u32,
+        signatures: Vec<Signature>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Block {
+        slot: u64,
+        transactions: Vec<Transaction>,
+    }
+
+    let block = Block {
+        slot: 1,
+        transactions: vec![
+            Transaction {
+                fee: 5,
+                signatures: vec![Signature { verified: true }],
+            },
+            Transaction {
+                fee: 6,
+                signatures: vec![Signature { verified: false }],
+            },
+        ],
+    };
+    let options = DefaultOptions::new().with_fixint_encoding();
+    let mut encoded = options.serialize(&block).unwrap();
+    // slot, length, first transaction, second fee and length
+    let offset = 8 + 8 + (4 + 8 + 1) + (4 + 8);
+    encoded[offset] = 2;
+
+    match *options
+        .with_error_context()
+        .deserialize::<Block>(&encoded)
+        .unwrap_err()
+    {
+        ErrorKind::Context {
+            offset: error_offset,
+            ref path,
+            ref source,
+        } => {
+            assert_eq!(error_offset, offset as u64);
+            assert_eq!(path, "Block.transactions[1].signatures[0].verified");
+            match **source {
+                ErrorKind::InvalidBoolEncoding(2) => {}
+                ref err => panic!("unexpected error: {}", err),
+            }
+        }
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // the error is reported as-is without the option
+    match *options.deserialize::<Block>(&encoded).unwrap_err() {
+        ErrorKind::InvalidBoolEncoding(2) => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // a truncated message reports the offset of the read that ran out of bytes
+    let truncated = &options.serialize(&block).unwrap()[..8 + 8 + 2];
+    match *options
+        .with_error_context()
+        .deserialize_from::<_, Block>(truncated)
+        .unwrap_err()
+    {
+        ErrorKind::Context {
+            offset, ref path, ..
+        } => {
+            assert_eq!(offset, 16);
+            assert_eq!(path, "Block.transactions[0].fee");
+        }
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_error_context_enum_and_map() {
+    #[derive(Serialize, Deserialize, Debug)]
+    enum Message {
+        Ping,
+        Transfer { to: (u8, u8), amount: Option<u8> },
+    }
+
+    let options = DefaultOptions::new().with_error_context();
+    let mut encoded = options
+        .serialize(&Message::Transfer {
+            to: (1, 2),
+            amount: Some(3),
+        })
+        .unwrap();
+    encoded[3] = 7;
+    let err = options.deserialize::<Message>(&encoded).unwrap_err();
+    match *err {
+        ErrorKind::Context {
+            offset, ref path, ..
+        } => {
+            assert_eq!(offset, 3);
+            assert_eq!(path, "Message::Transfer.amount");
+        }
+        ref err => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(
+        err.to_string(),
+        "tag for enum is not valid: 7 at byte offset 3 (Message::Transfer.amount)"
+    );
+
+    let mut map = HashMap::new();
+    map.insert(1u8, (2u8, true));
+    let mut encoded = options.serialize(&map).unwrap();
+    encoded[3] = 9;
+    match *options
+        .deserialize::<HashMap<u8, (u8, bool)>>(&encoded)
+        .unwrap_err()
+    {
+        ErrorKind::Context { ref path, .. } => assert_eq!(path, "[0].1"),
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_self_describing_untagged_and_internally_tagged() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Id {
+        Number(u64),
+        Name(String),
+        Pair(u8, u8),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(tag = "type")]
+    enum Request {
+        Get { id: Id },
+        Put { id: Id, data: Vec<u8> },
+        Ping,
+    }
+
+    let options = DefaultOptions::new().with_self_describing();
+    let requests = vec![
+        Request::Get { id: Id::Number(7) },
+        Request::Put {
+            id: Id::Name("key".to_string()),
+            data: vec![1, 2, 3],
+        },
+        Request::Get { id: Id::Pair(1, 2) },
+        Request::Ping,
+    ];
+    let encoded = options.serialize(&requests).unwrap();
+    assert_eq!(
+        options.serialized_size(&requests).unwrap(),
+        encoded.len() as u64
+    );
+    let decoded: Vec<Request> = options.deserialize(&encoded).unwrap();
+    assert_eq!(decoded, requests);
+
+    // without type tags there is nothing for deserialize_any to dispatch on
+    let encoded = DefaultOptions::new().serialize(&Id::Number(7)).unwrap();
+    match *DefaultOptions::new()
+        .deserialize::<Id>(&encoded)
+        .unwrap_err()
+    {
+        ErrorKind::DeserializeAnyNotSupported => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_self_describing_flatten_and_ignored_fields() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Header {
+        version: u16,
+        flags: Option<u8>,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Packet {
+        #[serde(flatten)]
+        header: Header,
+        body: String,
+        extra: HashMap<String, bool>,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct PacketBody {
+        body: String,
+    }
+
+    let options = DefaultOptions::new()
+        .with_self_describing()
+        .allow_unknown_lengths();
+    let mut extra = HashMap::new();
+    extra.insert("compressed".to_string(), true);
+    let packet = Packet {
+        header: Header {
+            version: 2,
+            flags: Some(3),
+        },
+        body: "hello".to_string(),
+        extra,
+    };
+    let encoded = options.serialize(&packet).unwrap();
+    let decoded: Packet = options.deserialize(&encoded).unwrap();
+    assert_eq!(decoded, packet);
+
+    // fields the target type does not know about are skipped with deserialize_ignored_any
+    let decoded: PacketBody = options.deserialize(&encoded).unwrap();
+    assert_eq!(decoded.body, "hello");
+}
+
+#[test]
+fn test_self_describing_dynamic_value() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Value {
+        Null,
+        Bool(bool),
+        Int(i64),
+        Float(f64),
+        Str(String),
+        List(Vec<Value>),
+        Map(std::collections::BTreeMap<String, Value>),
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { radius: f64 },
+        Dot,
+    }
+
+    #[derive(Serialize)]
+    struct Drawing {
+        name: &'static str,
+        shapes: Vec<Shape>,
+        layer: Option<u8>,
+    }
+
+    let options = DefaultOptions::new().with_self_describing();
+    let drawing = Drawing {
+        name: "logo",
+        shapes: vec![Shape::Circle { radius: 1.5 }, Shape::Dot],
+        layer: None,
+    };
+    let encoded = options.serialize(&drawing).unwrap();
+    let value: Value = options.deserialize(&encoded).unwrap();
+
+    let mut circle = std::collections::BTreeMap::new();
+    circle.insert("radius".to_string(), Value::Float(1.5));
+    let mut circle_variant = std::collections::BTreeMap::new();
+    circle_variant.insert("Circle".to_string(), Value::Map(circle));
+    let mut dot_variant = std::collections::BTreeMap::new();
+    dot_variant.insert("Dot".to_string(), Value::Null);
+    let mut expected = std::collections::BTreeMap::new();
+    expected.insert("name".to_string(), Value::Str("logo".to_string()));
+    expected.insert(
+        "shapes".to_string(),
+        Value::List(vec![Value::Map(circle_variant), Value::Map(dot_variant)]),
+    );
+    expected.insert("layer".to_string(), Value::Null);
+    assert_eq!(value, Value::Map(expected));
+
+    match *options.deserialize::<Value>(&[99]).unwrap_err() {
+        ErrorKind::InvalidTagEncoding(99) => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_compact_u16_lengths() {
+    use bincode::config::CompactU16;
+
+    let options = DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_compact_u16_lengths();
+
+    for &(len, ref prefix) in &[
+        (0usize, vec![0x00u8]),
+        (0x7f, vec![0x7f]),
+        (0x80, vec![0x80, 0x01]),
+        (0x3fff, vec![0xff, 0x7f]),
+        (0x4000, vec![0x80, 0x80, 0x01]),
+        (0xffff, vec![0xff, 0xff, 0x03]),
+    ] {
+        let value = vec![7u8; len];
+        let encoded = options.serialize(&value).unwrap();
+        assert_eq!(&encoded[..prefix.len()], &prefix[..]);
+        assert_eq!(encoded.len(), prefix.len() + len);
+        assert_eq!(
+            options.serialized_size(&value).unwrap(),
+            encoded.len() as u64
+        );
+        assert_eq!(options.deserialize::<Vec<u8>>(&encoded).unwrap(), value);
+    }
+
+    // integers keep the fixint encoding, in whichever order the two are set
+    assert_eq!(
+        options.serialize(&(vec![1u32], 2u16)).unwrap(),
+        vec![1, 1, 0, 0, 0, 2, 0]
+    );
+    let reordered = DefaultOptions::new()
+        .with_len_encoding::<CompactU16>()
+        .with_fixint_encoding();
+    assert_eq!(
+        reordered.serialize(&(vec![1u32], 2u16)).unwrap(),
+        vec![1, 1, 0, 0, 0, 2, 0]
+    );
+
+    // a length above u16::MAX is rejected by serialized_size as well as serialize
+    let too_long = vec![0u8; 0x10000];
+    for err in [
+        options.serialize(&too_long).unwrap_err(),
+        options.serialized_size(&too_long).unwrap_err(),
+    ] {
+        match *err {
+            ErrorKind::LengthOverflow {
+                len: 0x10000,
+                max: 0xffff,
+            } => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    // non-canonical encodings and a fourth byte
+    for bytes in &[
+        &[0x80u8, 0x00][..],
+        &[0xff, 0x80, 0x00],
+        &[0x80, 0x80, 0x80, 0x01],
+    ] {
+        match *options.deserialize::<Vec<u8>>(bytes).unwrap_err() {
+            ErrorKind::InvalidLengthEncoding => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+    }
+    match *options
+        .deserialize::<Vec<u8>>(&[0xff, 0xff, 0x04])
+        .unwrap_err()
+    {
+        ErrorKind::LengthOverflow {
+            len: 0x13fff,
+            max: 0xffff,
+        } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_len_encoding() {
+    use bincode::config::{FixU16, FixU32, FixU8, Varint};
+
+    let value = (vec![1u32, 2], "ab".to_string());
+
+    let options = DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_len_encoding::<Varint>();
+    let encoded = options.serialize(&value).unwrap();
+    assert_eq!(encoded, vec![2, 1, 0, 0, 0, 2, 0, 0, 0, 2, b'a', b'b']);
+    assert_eq!(
+        options.deserialize::<(Vec<u32>, String)>(&encoded).unwrap(),
+        value
+    );
+
+    let options = DefaultOptions::new()
+        .with_len_encoding::<FixU32>()
+        .with_varint_encoding();
+    let encoded = options.serialize(&value).unwrap();
+    assert_eq!(encoded, vec![2, 0, 0, 0, 1, 2, 2, 0, 0, 0, b'a', b'b']);
+    assert_eq!(
+        options.deserialize::<(Vec<u32>, String)>(&encoded).unwrap(),
+        value
+    );
+
+    let options = DefaultOptions::new()
+        .with_big_endian()
+        .with_len_encoding::<FixU16>();
+    let encoded = options.serialize(&vec![7u8; 300]).unwrap();
+    assert_eq!(&encoded[..2], &[1, 44]);
+    assert_eq!(options.serialized_size(&vec![7u8; 300]).unwrap(), 302);
+
+    let options = DefaultOptions::new().with_len_encoding::<FixU8>();
+    assert_eq!(options.serialize(&vec![7u8; 255]).unwrap().len(), 256);
+    match *options.serialize(&vec![7u8; 256]).unwrap_err() {
+        ErrorKind::LengthOverflow { len: 256, max: 255 } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    assert!(options.serialized_size(&vec![7u8; 256]).is_err());
+}
+
+#[test]
+fn test_enum_tag() {
+    use bincode::config::Varint;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Packet {
+        Ping,
+        Data(u16),
+        Ack { seq: u8 },
+    }
+
+    struct FarVariant(u32);
+
+    impl serde::Serialize for FarVariant {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+            serializer.serialize_unit_variant("Far", self.0, "Variant")
+        }
+    }
+
+    let packets = vec![Packet::Ping, Packet::Data(258), Packet::Ack { seq: 9 }];
+
+    let options = DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_enum_tag::<u8>();
+    let encoded = options.serialize(&packets).unwrap();
+    assert_eq!(&encoded[8..], &[0, 1, 2, 1, 2, 9]);
+    assert_eq!(
+        options.deserialize::<Vec<Packet>>(&encoded).unwrap(),
+        packets
+    );
+
+    let options = DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_big_endian()
+        .with_enum_tag::<u16>();
+    assert_eq!(
+        options.serialize(&Packet::Data(1)).unwrap(),
+        vec![0, 1, 0, 1]
+    );
+
+    let options = DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_enum_tag::<Varint>();
+    assert_eq!(
+        options.serialize(&Packet::Ack { seq: 9 }).unwrap(),
+        vec![2, 9]
+    );
+    assert_eq!(
+        options.serialize(&FarVariant(300)).unwrap(),
+        vec![251, 44, 1]
+    );
+
+    let options = DefaultOptions::new().with_enum_tag::<u32>();
+    assert_eq!(
+        options.serialize(&Packet::Ack { seq: 9 }).unwrap(),
+        vec![2, 0, 0, 0, 9]
+    );
+
+    let options = DefaultOptions::new().with_enum_tag::<u8>();
+    assert_eq!(options.serialize(&FarVariant(255)).unwrap(), vec![255]);
+    match *options.serialize(&FarVariant(256)).unwrap_err() {
+        ErrorKind::EnumTagOverflow {
+            index: 256,
+            max: 255,
+        } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    match *options.serialized_size(&FarVariant(256)).unwrap_err() {
+        ErrorKind::EnumTagOverflow {
+            index: 256,
+            max: 255,
+        } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_alloc_limit() {
+    // Each element is charged its inline size, and each string its contents.
+    let string_size = std::mem::size_of::<String>() as u64;
+    let strings_options = DefaultOptions::new().with_alloc_limit(3 * string_size + 25);
+
+    let strings = vec!["0123456789".to_string(), "0123456789".to_string()];
+    let encoded = strings_options.serialize(&strings).unwrap();
+    assert_eq!(
+        strings_options
+            .deserialize::<Vec<String>>(&encoded)
+            .unwrap(),
+        strings
+    );
+
+    let strings = vec!["0123456789".to_string(); 3];
+    let encoded = strings_options.serialize(&strings).unwrap();
+    match *strings_options
+        .deserialize::<Vec<String>>(&encoded)
+        .unwrap_err()
+    {
+        ErrorKind::AllocationLimit {
+            requested: 10,
+            remaining: 5,
+        } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // A huge declared length is rejected once the elements read so far fill the limit, rather
+    // than after the vector has grown to hold all of them.
+    let options = DefaultOptions::new().with_alloc_limit(1024);
+    let mut encoded = options.serialize(&100_000_000u64).unwrap();
+    encoded.extend_from_slice(&[7; 200 * 8]);
+    match *options.deserialize::<Vec<u64>>(&encoded).unwrap_err() {
+        ErrorKind::AllocationLimit {
+            requested: 8,
+            remaining: 0,
+        } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    let map = DefaultOptions::new()
+        .serialize(&(0..100u64).map(|i| (i, i)).collect::<HashMap<_, _>>())
+        .unwrap();
+    match *options.deserialize::<HashMap<u64, u64>>(&map).unwrap_err() {
+        ErrorKind::AllocationLimit { requested: 8, .. } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    let options = DefaultOptions::new().with_alloc_limit(25);
+
+    // A tiny input declaring a huge byte buffer is rejected before anything is allocated.
+    let encoded = DefaultOptions::new().serialize(&(u32::MAX as u64)).unwrap();
+    match *options
+        .deserialize::<serde_bytes::ByteBuf>(&encoded)
+        .unwrap_err()
+    {
+        ErrorKind::AllocationLimit {
+            requested,
+            remaining: 25,
+        } if requested == u32::MAX as u64 => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // Borrowed strings are not allocated when reading from a slice, but they are copied into a
+    // buffer when reading from an io::Read.
+    let long = "a".repeat(100);
+    let encoded = options.serialize(&long).unwrap();
+    assert_eq!(options.deserialize::<&str>(&encoded).unwrap(), long);
+    match *options
+        .deserialize_from::<_, String>(&encoded[..])
+        .unwrap_err()
+    {
+        ErrorKind::AllocationLimit {
+            requested: 100,
+            remaining: 25,
+        } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_max_depth() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Nested {
+        Leaf,
+        Node(Box<Nested>),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Chain(Option<Box<Chain>>);
+
+    let nested = Nested::Node(Box::new(Nested::Node(Box::new(Nested::Leaf))));
+    let options = DefaultOptions::new().with_max_depth(3);
+    let encoded = options.serialize(&nested).unwrap();
+    assert_eq!(options.deserialize::<Nested>(&encoded).unwrap(), nested);
+
+    let options = DefaultOptions::new().with_max_depth(2);
+    match *options.serialize(&nested).unwrap_err() {
+        ErrorKind::DepthLimitExceeded => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    match *options.deserialize::<Nested>(&encoded).unwrap_err() {
+        ErrorKind::DepthLimitExceeded => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    let nested = vec![vec![1u8, 2], vec![3]];
+    let encoded = options.serialize(&nested).unwrap();
+    assert_eq!(
+        options.deserialize::<Vec<Vec<u8>>>(&encoded).unwrap(),
+        nested
+    );
+    match *options.serialize(&vec![nested]).unwrap_err() {
+        ErrorKind::DepthLimitExceeded => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // A few bytes per level would overflow the stack without a limit.
+    let options = DefaultOptions::new().with_max_depth(64);
+    let encoded = vec![1u8; 1_000_000];
+    match *options.deserialize::<Chain>(&encoded).unwrap_err() {
+        ErrorKind::DepthLimitExceeded => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    // 18 is the type tag of `Some` in the self-describing format
+    let encoded = vec![18u8; 1_000_000];
+    match *options
+        .with_self_describing()
+        .deserialize::<Chain>(&encoded)
+        .unwrap_err()
+    {
+        ErrorKind::DepthLimitExceeded => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_max_collection_len() {
+    let options = DefaultOptions::new().with_max_collection_len(3);
+
+    let small: HashMap<u8, Vec<u8>> = vec![(1, vec![1, 2, 3])].into_iter().collect();
+    let encoded = options.serialize(&small).unwrap();
+    assert_eq!(
+        options
+            .deserialize::<HashMap<u8, Vec<u8>>>(&encoded)
+            .unwrap(),
+        small
+    );
+
+    let encoded = options.serialize(&vec![0u32; 4]).unwrap();
+    match *options.deserialize::<Vec<u32>>(&encoded).unwrap_err() {
+        ErrorKind::CollectionLengthLimit { len: 4, max: 3 } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    let encoded = options.serialize("four").unwrap();
+    match *options.deserialize::<String>(&encoded).unwrap_err() {
+        ErrorKind::CollectionLengthLimit { len: 4, max: 3 } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // The length is rejected before any of the elements are read.
+    let encoded = options.serialize(&(1u64 << 60)).unwrap();
+    match *options.deserialize::<Vec<u8>>(&encoded).unwrap_err() {
+        ErrorKind::CollectionLengthLimit { len, max: 3 } if len == 1 << 60 => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_serialize_into_slice() {
+    let value = (1u32, "hello".to_string(), vec![1u8, 2, 3]);
+    let options = DefaultOptions::new();
+    let expected = options.serialize(&value).unwrap();
+
+    let mut buf = [0u8; 64];
+    let written = options.serialize_into_slice(&mut buf, &value).unwrap();
+    assert_eq!(&buf[..written], &expected[..]);
+
+    let mut buf = vec![0u8; expected.len()];
+    assert_eq!(
+        options.serialize_into_slice(&mut buf, &value).unwrap(),
+        expected.len()
+    );
+    assert_eq!(buf, expected);
+
+    let mut buf = [0u8; 4];
+    match *options.serialize_into_slice(&mut buf, &value).unwrap_err() {
+        ErrorKind::BufferTooSmall {
+            needed,
+            available: 4,
+        } if needed == expected.len() as u64 => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // With a size limit, the size is checked before anything is written.
+    let mut buf = [0u8; 4];
+    match *options
+        .with_limit(100)
+        .serialize_into_slice(&mut buf, &value)
+        .unwrap_err()
+    {
+        ErrorKind::BufferTooSmall {
+            needed,
+            available: 4,
+        } if needed == expected.len() as u64 => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(buf, [0u8; 4]);
+    match *options
+        .with_limit(4)
+        .serialize_into_slice(&mut [0u8; 64], &value)
+        .unwrap_err()
+    {
+        ErrorKind::SizeLimit => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+#[test]
+fn test_embedded_io() {
+    let value = (42u32, "hello".to_string(), vec![1u8, 2, 3]);
+    let options = DefaultOptions::new();
+
+    let mut out = [0u8; 64];
+    let mut writer = &mut out[..];
+    options
+        .serialize_into_embedded(&mut writer, &value)
+        .unwrap();
+    let written = 64 - writer.len();
+    assert_eq!(&out[..written], &options.serialize(&value).unwrap()[..]);
+
+    let decoded: (u32, String, Vec<u8>) = options.deserialize_from_embedded(&out[..]).unwrap();
+    assert_eq!(decoded, value);
+
+    match *options
+        .deserialize_from_embedded::<_, (u32, String, Vec<u8>)>(&out[..4])
+        .unwrap_err()
+    {
+        ErrorKind::Io(ref err) if err.kind() == bincode::io::ErrorKind::UnexpectedEof => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    let mut small = [0u8; 4];
+    match *options
+        .serialize_into_embedded(&mut small[..], &value)
+        .unwrap_err()
+    {
+        ErrorKind::Io(ref err) if err.kind() == bincode::io::ErrorKind::WriteZero => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_tokio_codec() {
+    use bincode::codec::{BincodeCodec, FrameHeader};
+    use bytes::BytesMut;
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::codec::{Decoder, FramedRead, FramedWrite};
+
+    type Message = (u32, String, Vec<u8>);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let messages: Vec<Message> = (0..20)
+            .map(|i| (i, "x".repeat(i as usize), vec![i as u8; 3 * i as usize]))
+            .collect();
+
+        // a small duplex buffer splits frames across reads
+        let (client, server) = tokio::io::duplex(16);
+        let options = DefaultOptions::new().with_big_endian();
+        let codec = BincodeCodec::<Message, _>::new(options).with_header(FrameHeader::U16);
+        let mut writer = FramedWrite::new(client, codec.clone());
+        let mut reader = FramedRead::new(server, codec);
+
+        let sent = messages.clone();
+        let send = async move {
+            for message in sent {
+                writer.send(message).await.unwrap();
+            }
+        };
+        let receive = async {
+            let mut received = Vec::new();
+            while received.len() < messages.len() {
+                received.push(reader.next().await.unwrap().unwrap());
+            }
+            received
+        };
+        let ((), received) = futures::join!(send, receive);
+        assert_eq!(received, messages);
+
+        // a frame larger than the limit is rejected from its header alone
+        let (mut client, server) = tokio::io::duplex(64);
+        let options = DefaultOptions::new().with_limit(100);
+        let mut reader = FramedRead::new(server, BincodeCodec::<Message, _>::new(options));
+        client.write_all(&1000u32.to_le_bytes()).await.unwrap();
+        match *reader.next().await.unwrap().unwrap_err() {
+            ErrorKind::SizeLimit => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+
+        // so is a frame larger than the allocation limit, and without any limit an oversized
+        // header does not make the codec reserve the length it announces
+        let header = (1u64 << 52).to_le_bytes();
+        let mut codec =
+            BincodeCodec::<Message, _>::new(DefaultOptions::new().with_alloc_limit(1024))
+                .with_header(FrameHeader::U64);
+        match *codec.decode(&mut BytesMut::from(&header[..])).unwrap_err() {
+            ErrorKind::AllocationLimit {
+                requested,
+                remaining: 1024,
+            } if requested == 1 << 52 => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+        let mut codec =
+            BincodeCodec::<Message, _>::new(DefaultOptions::new()).with_header(FrameHeader::U64);
+        let mut src = BytesMut::from(&header[..]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        assert!(src.capacity() < 1024);
+
+        // and a value larger than the limit is not encoded
+        let mut writer = FramedWrite::new(Vec::new(), BincodeCodec::<Message, _>::new(options));
+        match *writer
+            .send((0, String::new(), vec![0; 200]))
+            .await
+            .unwrap_err()
+        {
+            ErrorKind::SizeLimit => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+    });
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_futures_io() {
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+
+    /// Returns at most 3 bytes from every read, and is pending before every other read.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        pending: bool,
+    }
+
+    impl<'a> futures::io::AsyncRead for Trickle<'a> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = buf.len().min(self.bytes.len()).min(3);
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    /// Has all of its bytes ready.
+    struct Ready<'a> {
+        bytes: &'a [u8],
+    }
+
+    impl<'a> futures::io::AsyncRead for Ready<'a> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let n = buf.len().min(self.bytes.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how many times deserialization starts over.
+    #[derive(Debug, PartialEq)]
+    struct Counted(Vec<u16>);
+
+    impl<'de> Deserialize<'de> for Counted {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+            ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+            Vec::deserialize(deserializer).map(Counted)
+        }
+    }
+
+    type Message = (u32, String, Vec<u16>, Option<[u64; 2]>);
+
+    futures::executor::block_on(async {
+        let options = DefaultOptions::new().with_big_endian();
+        let first: Message = (7, "hello".into(), vec![1, 300, 65535], Some([1, 2]));
+        let second: Message = (8, "x".repeat(100), Vec::new(), None);
+
+        let mut out = Vec::new();
+        options
+            .serialize_into_async(&mut out, &first)
+            .await
+            .unwrap();
+        options
+            .serialize_into_async(&mut out, &second)
+            .await
+            .unwrap();
+        let mut expected = options.serialize(&first).unwrap();
+        expected.extend(options.serialize(&second).unwrap());
+        assert_eq!(out, expected);
+
+        // a reader that is pending after every read is only asked for the missing bytes, so
+        // nothing past the end of the first value is read
+        let mut reader = Trickle {
+            bytes: &out,
+            pending: false,
+        };
+        let decoded: Message = options.deserialize_from_async(&mut reader).await.unwrap();
+        assert_eq!(decoded, first);
+        let decoded: Message = options.deserialize_from_async(&mut reader).await.unwrap();
+        assert_eq!(decoded, second);
+        assert!(reader.bytes.is_empty());
+
+        // a reader with everything ready is not read past the end of a value either, and a
+        // long value is deserialized once
+        let long: Vec<u16> = (0..10_000).collect();
+        let mut bytes = options.serialize(&long).unwrap();
+        bytes.extend(options.serialize(&first).unwrap());
+        let mut reader = Ready { bytes: &bytes };
+        let decoded: Counted = options.deserialize_from_async(&mut reader).await.unwrap();
+        assert_eq!(decoded, Counted(long));
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 1);
+        let decoded: Message = options.deserialize_from_async(&mut reader).await.unwrap();
+        assert_eq!(decoded, first);
+        assert!(reader.bytes.is_empty());
+
+        // a value the reader is pending in the middle of starts over once the read it stopped
+        // at can be completed
+        let short: Vec<u16> = vec![1, 2, 3, 4, 5, 6];
+        let bytes = options.serialize(&short).unwrap();
+        let mut reader = Trickle {
+            bytes: &bytes,
+            pending: false,
+        };
+        ATTEMPTS.store(0, Ordering::SeqCst);
+        let decoded: Counted = options.deserialize_from_async(&mut reader).await.unwrap();
+        assert_eq!(decoded, Counted(short));
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 1 + bytes.len());
+
+        // a truncated value
+        let mut reader = Trickle {
+            bytes: &out[..5],
+            pending: false,
+        };
+        match *options
+            .deserialize_from_async::<_, Message>(&mut reader)
+            .await
+            .unwrap_err()
+        {
+            ErrorKind::Io(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+
+        // the limit is checked before the bytes are read
+        let options = options.with_limit(10);
+        let mut reader = Trickle {
+            bytes: &out,
+            pending: false,
+        };
+        match *options
+            .deserialize_from_async::<_, Message>(&mut reader)
+            .await
+            .unwrap_err()
+        {
+            ErrorKind::SizeLimit => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+        let mut out = Vec::new();
+        match *options
+            .serialize_into_async(&mut out, &second)
+            .await
+            .unwrap_err()
+        {
+            ErrorKind::SizeLimit => {}
+            ref err => panic!("unexpected error: {}", err),
+        }
+        assert!(out.is_empty());
+    });
+}
+
+#[test]
+fn test_incremental_decoder() {
+    use bincode::decoder::Decoder;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Poll;
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how many times deserialization starts over.
+    #[derive(Debug, PartialEq)]
+    struct Counted(Vec<String>);
+
+    impl<'de> Deserialize<'de> for Counted {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+            ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+            Vec::deserialize(deserializer).map(Counted)
+        }
+    }
+
+    let options = DefaultOptions::new();
+    let strings: Vec<String> = (0..50).map(|i| "y".repeat(i)).collect();
+    let mut bytes = options.serialize(&strings).unwrap();
+    let len = bytes.len();
+    bytes.extend_from_slice(&[0xAA, 0xBB]);
+
+    let mut decoder = Decoder::<Counted, _>::new(options);
+    let mut fed = 0;
+    let value = loop {
+        assert!(decoder.needed() >= 1);
+        assert!(fed + decoder.needed() <= len);
+        let end = if fed + 1 == len { len + 2 } else { fed + 1 };
+        match decoder.feed(&bytes[fed..end]) {
+            Poll::Ready(value) => break value.unwrap(),
+            Poll::Pending => fed = end,
+        }
+    };
+    assert_eq!(value, Counted(strings));
+    // fed a byte at a time, the value is only deserialized again once the length or the
+    // contents of the string it stopped at have arrived
+    let attempts = ATTEMPTS.load(Ordering::SeqCst);
+    assert!(attempts <= 2 * 50 + 2, "{} attempts", attempts);
+    assert_eq!(decoder.remainder(), &[0xAA, 0xBB]);
+    assert!(decoder.feed(&[0]).is_ready());
+
+    // a length prefix tells the decoder how many bytes a string needs
+    let mut decoder = Decoder::<String, _>::new(options);
+    assert!(decoder.feed(&[100]).is_pending());
+    assert_eq!(decoder.needed(), 100);
+
+    // invalid input is reported as soon as it is fed
+    let mut decoder = Decoder::<String, _>::new(options);
+    match decoder.feed(&[2, 0xFF, 0xFF]) {
+        Poll::Ready(Err(err)) => match *err {
+            ErrorKind::InvalidUtf8Encoding(_) => {}
+            ref err => panic!("unexpected error: {}", err),
+        },
+        _ => panic!("the invalid string was not reported"),
+    }
+
+    static BYTES_READ: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts the bytes deserialized.
+    #[derive(Debug, PartialEq)]
+    struct Byte(u8);
+
+    impl<'de> Deserialize<'de> for Byte {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+            let byte = u8::deserialize(deserializer)?;
+            BYTES_READ.fetch_add(1, Ordering::SeqCst);
+            Ok(Byte(byte))
+        }
+    }
+
+    // every attempt reads the buffered bytes again from the start, so feeding 100 bytes in 4
+    // fragments reads 25 + 50 + 75 + 100 of them
+    let bytes = options.serialize(&vec![7u8; 100]).unwrap();
+    let mut decoder = Decoder::<Vec<Byte>, _>::new(options);
+    for fragment in [&bytes[..26], &bytes[26..51], &bytes[51..76]] {
+        assert!(decoder.feed(fragment).is_pending());
+    }
+    match decoder.feed(&bytes[76..]) {
+        Poll::Ready(value) => assert_eq!(value.unwrap().len(), 100),
+        Poll::Pending => panic!("the value is complete"),
+    }
+    assert_eq!(BYTES_READ.load(Ordering::SeqCst), 250);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_bytes_buf() {
+    use bytes::{Buf, Bytes, BytesMut};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Packet {
+        id: u32,
+        name: String,
+        #[serde(with = "bincode::buf::shared")]
+        payload: Bytes,
+    }
+
+    let options = DefaultOptions::new();
+    let packets: Vec<Packet> = (0..3)
+        .map(|i| Packet {
+            id: i,
+            name: "p".repeat(i as usize),
+            payload: Bytes::from(vec![i as u8; 10]),
+        })
+        .collect();
+
+    let mut buf = BytesMut::new();
+    for packet in &packets {
+        options.serialize_into_buf(&mut buf, packet).unwrap();
+    }
+    let bytes = buf.freeze();
+
+    // consecutive values are read from the same buffer, even when they span its chunks
+    let (head, tail) = (bytes.slice(..7), bytes.slice(7..));
+    let mut chain = head.chain(tail);
+    for packet in &packets {
+        let read: Packet = options.deserialize_from_buf(&mut chain).unwrap();
+        assert_eq!(&read, packet);
+    }
+    assert!(!chain.has_remaining());
+
+    // with `std`, a value deserialized from `Bytes` shares its memory
+    let single = Bytes::from(options.serialize(&packets[2]).unwrap());
+    let read: Packet = options.deserialize_from_bytes(&single).unwrap();
+    assert_eq!(read, packets[2]);
+    let start = single.as_ptr() as usize;
+    let payload = read.payload.as_ptr() as usize;
+    let shared = payload >= start && payload + 10 <= start + single.len();
+    assert_eq!(shared, cfg!(feature = "std"));
+
+    // a value that panics while it is deserialized from `Bytes` does not leave them behind as
+    // the source that later values are sliced from
+    struct Panics;
+
+    impl<'de> Deserialize<'de> for Panics {
+        fn deserialize<D: Deserializer<'de>>(_: D) -> StdResult<Self, D::Error> {
+            panic!("Panics is never deserialized");
+        }
+    }
+
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        options.deserialize_from_bytes::<Panics>(&single)
+    }));
+    assert!(panicked.is_err());
+    let read: Packet = options.deserialize(&single[..]).unwrap();
+    let payload = read.payload.as_ptr() as usize;
+    assert!(payload + 10 <= start || payload >= start + single.len());
+
+    // running out of bytes is an error
+    let mut short = single.slice(..5);
+    assert!(options
+        .deserialize_from_buf::<_, Packet>(&mut short)
+        .is_err());
+
+    // a buffer that can not grow reports the value that did not fit
+    let mut array = [0u8; 4];
+    assert!(options
+        .serialize_into_buf(&mut &mut array[..], &packets[2])
+        .is_err());
+}
+
+#[test]
+fn test_deserialize_partial() {
+    use bincode::de::Deserializer;
+
+    let options = DefaultOptions::new().with_varint_encoding();
+    let messages = vec![
+        (1u64, "a".to_string()),
+        (300, "bb".repeat(200)),
+        (u64::MAX, String::new()),
+    ];
+    let mut bytes = Vec::new();
+    for message in &messages {
+        bytes.extend(options.serialize(message).unwrap());
+    }
+    bytes.push(0xFF);
+
+    let mut rest = &bytes[..];
+    for message in &messages {
+        let (read, tail): ((u64, String), _) = options.deserialize_partial(rest).unwrap();
+        assert_eq!(&read, message);
+        assert_eq!(
+            rest.len() - tail.len(),
+            options.serialized_size(message).unwrap() as usize
+        );
+        rest = tail;
+    }
+    assert_eq!(rest, &[0xFF]);
+
+    // the deserializer counts every byte it reads, including varints and strings
+    let mut deserializer = Deserializer::from_slice(&bytes, options);
+    for message in &messages {
+        let read = <(u64, String)>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(&read, message);
+        assert_eq!(
+            deserializer.bytes_read() as usize,
+            bytes.len() - deserializer.remaining().len()
+        );
+    }
+    assert_eq!(deserializer.remaining(), &[0xFF]);
+
+    // errors are still reported
+    assert!(options
+        .deserialize_partial::<(u64, String)>(&[1, 5, b'a'])
+        .is_err());
+}
+
+#[test]
+fn test_deserialize_stream() {
+    let options = DefaultOptions::new().with_limit(64);
+    let records: Vec<(u32, String)> = (0..10).map(|i| (i, "r".repeat(i as usize))).collect();
+    let mut log = Vec::new();
+    for record in &records {
+        options.serialize_into(&mut log, record).unwrap();
+    }
+    // the log as a whole is larger than the limit, but every record fits in it
+    assert!(log.len() > 64);
+
+    let read: Vec<(u32, String)> = options
+        .deserialize_stream(&log[..])
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(read, records);
+
+    // an empty log has no records
+    assert!(options
+        .deserialize_stream::<(u32, String), _>(&[][..])
+        .next()
+        .is_none());
+
+    // a log that ends in the middle of a record is truncated, not finished
+    let mut stream = options.deserialize_stream::<(u32, String), _>(&log[..log.len() - 1]);
+    for record in &records[..9] {
+        assert_eq!(&stream.next().unwrap().unwrap(), record);
+    }
+    match *stream.next().unwrap().unwrap_err() {
+        #[cfg(feature = "std")]
+        ErrorKind::Io(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {}
+        #[cfg(not(feature = "std"))]
+        ErrorKind::Io(_) => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    assert!(stream.next().is_none());
+
+    // a record larger than the limit is rejected
+    let mut big = Vec::new();
+    options
+        .serialize_into(&mut big, &(0u32, "x".repeat(40)))
+        .unwrap();
+    let mut stream = DefaultOptions::new()
+        .with_limit(32)
+        .deserialize_stream::<(u32, String), _>(&big[..]);
+    match *stream.next().unwrap().unwrap_err() {
+        ErrorKind::SizeLimit => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_framed() {
+    use bincode::frame::{FrameHeader, Framing};
+
+    let options = DefaultOptions::new();
+    let value = (7u32, "snapshot".to_string(), vec![1u8; 300]);
+    let headers = [
+        (FrameHeader::U16, 2),
+        (FrameHeader::U32, 4),
+        (FrameHeader::U64, 8),
+        (FrameHeader::Varint, 3),
+    ];
+    let payload = options.serialize(&value).unwrap();
+
+    for &(header, width) in &headers {
+        for &checksum in &[false, true] {
+            let framing = Framing::new().with_header(header);
+            let framing = if checksum {
+                framing.with_checksum()
+            } else {
+                framing
+            };
+            let mut bytes = Vec::new();
+            options
+                .serialize_framed(framing, &mut bytes, &value)
+                .unwrap();
+            bytes.extend_from_slice(b"next");
+            let trailer = if checksum { 4 } else { 0 };
+            assert_eq!(bytes.len(), width + payload.len() + trailer + 4);
+            assert_eq!(&bytes[width..width + payload.len()], &payload[..]);
+
+            let mut reader = &bytes[..];
+            let read: (u32, String, Vec<u8>) =
+                options.deserialize_framed(framing, &mut reader).unwrap();
+            assert_eq!(read, value);
+            assert_eq!(reader, b"next");
+        }
+    }
+
+    // a corrupted payload is caught by the checksum
+    let framing = Framing::new().with_checksum();
+    let mut bytes = Vec::new();
+    options
+        .serialize_framed(framing, &mut bytes, &value)
+        .unwrap();
+    bytes[10] ^= 1;
+    match *options
+        .deserialize_framed::<_, (u32, String, Vec<u8>)>(framing, &bytes[..])
+        .unwrap_err()
+    {
+        ErrorKind::ChecksumMismatch { expected, actual } => assert_ne!(expected, actual),
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // a frame longer than the limit is rejected before its payload is read
+    let mut bytes = Vec::new();
+    options
+        .serialize_framed(Framing::new(), &mut bytes, &value)
+        .unwrap();
+    match *options
+        .with_limit(100)
+        .deserialize_framed::<_, (u32, String, Vec<u8>)>(Framing::new(), &bytes[..4])
+        .unwrap_err()
+    {
+        ErrorKind::SizeLimit => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // so is a frame longer than the allocation limit, and without a limit a hostile header does
+    // not allocate the length it announces
+    let framing = Framing::new().with_header(FrameHeader::U64);
+    let header = (1u64 << 52).to_le_bytes();
+    match *options
+        .with_alloc_limit(1024)
+        .deserialize_framed::<_, Vec<u8>>(framing, &header[..])
+        .unwrap_err()
+    {
+        ErrorKind::AllocationLimit {
+            requested,
+            remaining: 1024,
+        } if requested == 1 << 52 => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+    let mut bytes = header.to_vec();
+    bytes.extend_from_slice(&[0; 100]);
+    match *options
+        .deserialize_framed::<_, Vec<u8>>(framing, &bytes[..])
+        .unwrap_err()
+    {
+        #[cfg(feature = "std")]
+        ErrorKind::Io(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {}
+        #[cfg(not(feature = "std"))]
+        ErrorKind::Io(_) => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // and a value that does not fit in the header is not written
+    let mut bytes = Vec::new();
+    let framing = Framing::new().with_header(FrameHeader::U16);
+    assert!(options
+        .serialize_framed(framing, &mut bytes, &vec![0u8; 70_000])
+        .is_err());
+    assert!(bytes.is_empty());
+}
+
+#[test]
+fn test_envelope() {
+    use bincode::envelope::Envelope;
+
+    let value = (1u32, "state".to_string(), vec![7u64; 3]);
+    let envelope = Envelope::new(2);
+    let mut bytes = Vec::new();
+    DefaultOptions::new()
+        .serialize_enveloped(envelope, &mut bytes, &value)
+        .unwrap();
+    assert_eq!(&bytes[..4], b"BNCD");
+    assert_eq!(
+        &bytes[12..],
+        &DefaultOptions::new().serialize(&value).unwrap()[..]
+    );
+
+    let read: (u32, String, Vec<u64>) = DefaultOptions::new()
+        .deserialize_enveloped(envelope, &bytes)
+        .unwrap();
+    assert_eq!(read, value);
+
+    // limits and error context do not change the format
+    let read: (u32, String, Vec<u64>) = DefaultOptions::new()
+        .with_limit(1000)
+        .with_error_context()
+        .deserialize_enveloped(envelope, &bytes)
+        .unwrap();
+    assert_eq!(read, value);
+
+    fn check<O: Options>(options: O, bytes: &[u8]) {
+        match *options
+            .deserialize_enveloped::<(u32, String, Vec<u64>)>(Envelope::new(2), bytes)
+            .unwrap_err()
+        {
+            ErrorKind::FingerprintMismatch { expected, found } => assert_ne!(expected, found),
+            ref err => panic!("unexpected error: {}", err),
+        }
+    }
+    check(DefaultOptions::new().with_fixint_encoding(), &bytes);
+    check(DefaultOptions::new().with_big_endian(), &bytes);
+    check(DefaultOptions::new().allow_trailing_bytes(), &bytes);
+    check(DefaultOptions::new().with_enum_tag::<u8>(), &bytes);
+    check(DefaultOptions::new().with_compact_u16_lengths(), &bytes);
+    check(DefaultOptions::new().with_self_describing(), &bytes);
+    check(DefaultOptions::new().with_struct_evolution(), &bytes);
+    check(DefaultOptions::new().with_variant_evolution(), &bytes);
+
+    // the configuration of the `serialize()` family of functions is told apart as well
+    let mut legacy = Vec::new();
+    DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .serialize_enveloped(envelope, &mut legacy, &value)
+        .unwrap();
+    check(DefaultOptions::new(), &legacy);
+
+    match *DefaultOptions::new()
+        .deserialize_enveloped::<(u32, String, Vec<u64>)>(Envelope::new(3), &bytes)
+        .unwrap_err()
+    {
+        ErrorKind::SchemaVersionMismatch {
+            expected: 3,
+            found: 2,
+        } => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    match *DefaultOptions::new()
+        .deserialize_enveloped::<(u32, String, Vec<u64>)>(envelope.with_magic(*b"ELSE"), &bytes)
+        .unwrap_err()
+    {
+        ErrorKind::InvalidMagic => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // the limits of the options do not apply to the fingerprint
+    let options = DefaultOptions::new().with_max_depth(1);
+    let mut bytes = Vec::new();
+    options
+        .serialize_enveloped(Envelope::new(1), &mut bytes, &42u64)
+        .unwrap();
+    assert_eq!(
+        options
+            .deserialize_enveloped::<u64>(Envelope::new(1), &bytes)
+            .unwrap(),
+        42
+    );
+}
+
+#[test]
+fn test_struct_evolution() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct AccountV1 {
+        id: u64,
+        owner: String,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct AccountV2 {
+        id: u64,
+        owner: String,
+        #[serde(default)]
+        balance: u64,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Event<A> {
+        Opened { account: A, slot: u32 },
+        Closed(u64),
+    }
+
+    let options = DefaultOptions::new().with_struct_evolution();
+    let v1 = AccountV1 {
+        id: 7,
+        owner: "alice".to_string(),
+    };
+    let v2 = AccountV2 {
+        id: 7,
+        owner: "alice".to_string(),
+        balance: 1000,
+        tags: vec!["validator".to_string()],
+    };
+
+    // the fields are prefixed by their length in bytes
+    let bytes = options.serialize(&v1).unwrap();
+    assert_eq!(bytes[0] as usize, bytes.len() - 1);
+    assert_eq!(options.serialized_size(&v1).unwrap(), bytes.len() as u64);
+    assert_eq!(options.deserialize::<AccountV1>(&bytes).unwrap(), v1);
+
+    // a newer reader fills in the appended fields with their defaults
+    let read: AccountV2 = options.deserialize(&bytes).unwrap();
+    assert_eq!(read.owner, "alice");
+    assert_eq!(read.balance, 0);
+    assert!(read.tags.is_empty());
+
+    // and an older reader skips them, also inside enums and sequences
+    let bytes = options.serialize(&v2).unwrap();
+    assert_eq!(options.serialized_size(&v2).unwrap(), bytes.len() as u64);
+    assert_eq!(options.deserialize::<AccountV1>(&bytes).unwrap(), v1);
+
+    // skipped fields are read through a small buffer instead of being allocated
+    let big = AccountV2 {
+        id: 7,
+        owner: "alice".to_string(),
+        balance: 0,
+        tags: vec!["x".repeat(10_000)],
+    };
+    let bytes = options.serialize(&big).unwrap();
+    let read: AccountV1 = options
+        .with_alloc_limit(1024)
+        .deserialize_from(&bytes[..])
+        .unwrap();
+    assert_eq!(read, v1);
+
+    // a length past the byte limit is rejected before any field is read
+    let bytes = options.serialize(&(1u64 << 40)).unwrap();
+    match *options
+        .with_limit(1000)
+        .deserialize_from::<_, AccountV1>(&bytes[..])
+        .unwrap_err()
+    {
+        ErrorKind::SizeLimit => {}
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    let events = vec![
+        Event::Opened {
+            account: v2,
+            slot: 3,
+        },
+        Event::Closed(7),
+    ];
+    let bytes = options.serialize(&events).unwrap();
+    assert_eq!(
+        options.serialized_size(&events).unwrap(),
+        bytes.len() as u64
+    );
+    let read: Vec<Event<AccountV1>> = options.deserialize(&bytes).unwrap();
+    assert_eq!(
+        read,
+        vec![
+            Event::Opened {
+                account: v1.clone(),
+                slot: 3
+            },
+            Event::Closed(7)
+        ]
+    );
+
+    // a seekable writer has the lengths backpatched instead of buffered
+    let options = options.with_fixint_encoding();
+    #[cfg(feature = "std")]
+    {
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        options
+            .serialize_into_seekable(&mut cursor, &events)
+            .unwrap();
+        assert_eq!(cursor.get_ref(), &options.serialize(&events).unwrap());
+    }
+
+    // a struct whose fields run past its length is rejected
+    let mut bytes = options.serialize(&v1).unwrap();
+    assert_eq!(bytes[..8], 21u64.to_le_bytes());
+    bytes[0] = 18;
+    match *options.deserialize::<AccountV1>(&bytes).unwrap_err() {
+        ErrorKind::Custom(ref msg) => assert!(msg.contains("3 bytes past"), "{}", msg),
+        ref err => panic!("unexpected error: {}", err),
+    }
+
+    // self-describing data already tolerates new fields, and is not changed
+    let options = DefaultOptions::new().with_self_describing();
+    assert_eq!(
+        options.with_struct_evolution().serialize(&events).unwrap(),
+        options.serialize(&events).unwrap()
+    );
+}
+
+#[test]
+fn test_variant_evolution() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum MessageV2 {
+        Ping,
+        Vote { slot: u64, hash: [u8; 4] },
+        Transfer(u64, String),
+        Gossip(Vec<u32>),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum MessageV1 {
+        Ping,
+        Vote {
+            slot: u64,
+        },
+        #[serde(other)]
+        Other,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Partial {
+        Ping,
+        Vote { slot: u64, hash: [u8; 4] },
+    }
+
+    let options = DefaultOptions::new().with_variant_evolution();
+    let messages = vec![
+        MessageV2::Ping,
+        MessageV2::Vote {
+            slot: 9,
+            hash: [1, 2, 3, 4],
+        },
+        MessageV2::Transfer(500, "bob".to_string()),
+        MessageV2::Gossip(vec![1, 2, 3]),
+    ];
+
+    // the payload of every variant follows its index and length
+    assert_eq!(options.serialize(&MessageV2::Ping).unwrap(), vec![0, 0]);
+    assert_eq!(
+        options.serialize(&MessageV2::Gossip(vec![7])).unwrap(),
+        vec![3, 2, 1, 7]
+    );
+    let bytes = options.serialize(&messages).unwrap();
+    assert_eq!(
+        options.serialized_size(&messages).unwrap(),
+        bytes.len() as u64
+    );
+    assert_eq!(
+        options.deserialize::<Vec<MessageV2>>(&bytes).unwrap(),
+        messages
+    );
+
+    // an older reader maps the variants it does not know to `#[serde(other)]`, and skips the
+    // fields appended to a struct variant
+    assert_eq!(
+        options.deserialize::<Vec<MessageV1>>(&bytes).unwrap(),
+        vec![
+            MessageV1::Ping,
+            MessageV1::Vote { slot: 9 },
+            MessageV1::Other,
+            MessageV1::Other,
+        ]
+    );
+
+    // or keeps them along with their payload, and writes them back out as they were
+    let transfer = options.serialize(&(500u64, "bob")).unwrap();
+    let read: Vec<OrUnknown<Partial>> = options.deserialize(&bytes).unwrap();
+    assert_eq!(
+        read,
+        vec![
+            OrUnknown::Known(Partial::Ping),
+            OrUnknown::Known(Partial::Vote {
+                slot: 9,
+                hash: [1, 2, 3, 4],
+            }),
+            OrUnknown::Unknown(UnknownVariant {
+                index: 2,
+                payload: transfer,
+            }),
+            OrUnknown::Unknown(UnknownVariant {
+                index: 3,
+                payload: vec![3, 1, 2, 3],
+            }),
+        ]
+    );
+    assert_eq!(options.serialize(&read).unwrap(), bytes);
+    assert_eq!(options.serialized_size(&read).unwrap(), bytes.len() as u64);
+
+    // the variants an enum has are known, including its `#[serde(other)]` variant
+    assert_eq!(
+        options
+            .deserialize::<Vec<OrUnknown<MessageV1>>>(&bytes)
+            .unwrap(),
+        vec![
+            OrUnknown::Known(MessageV1::Ping),
+            OrUnknown::Known(MessageV1::Vote { slot: 9 }),
+            OrUnknown::Known(MessageV1::Other),
+            OrUnknown::Unknown(UnknownVariant {
+                index: 3,
+                payload: vec![3, 1, 2, 3],
+            }),
+        ]
+    );
+
+    // a newer reader fills in the fields an older writer did not have
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum MessageV3 {
+        Ping,
+        Vote {
+            slot: u64,
+            #[serde(default)]
+            hash: [u8; 4],
+        },
+    }
+    let bytes = options.serialize(&MessageV1::Vote { slot: 9 }).unwrap();
+    assert_eq!(
+        options.deserialize::<MessageV3>(&bytes).unwrap(),
+        MessageV3::Vote {
+            slot: 9,
+            hash: [0; 4]
+        }
+    );
+
+    // without the length, the payload of an unknown variant cannot be skipped
+    let bytes = DefaultOptions::new()
+        .serialize(&MessageV2::Gossip(vec![]))
+        .unwrap();
+    assert!(DefaultOptions::new()
+        .deserialize::<MessageV1>(&bytes)
+        .is_err());
+
+    // a seekable writer has the lengths backpatched instead of buffered
+    #[cfg(feature = "std")]
+    {
+        let options = options.with_fixint_encoding();
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        options
+            .serialize_into_seekable(&mut cursor, &messages)
+            .unwrap();
+        assert_eq!(cursor.get_ref(), &options.serialize(&messages).unwrap());
+    }
+}